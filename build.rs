@@ -6,113 +6,502 @@
 )]
 
 extern crate bindgen;
-extern crate reqwest;
-extern crate unzip;
 use bindgen::BindgenError;
-use std::collections::HashMap;
-use std::fs::{DirEntry, OpenOptions};
+use std::fs::OpenOptions;
 use std::io::{self, BufRead, BufReader, BufWriter, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::{env, fs};
-use unzip::Unzipper;
+
+/// Files written by bindgen, which are also what gets bundled
+const BINDINGS: [&str; 3] = ["snpe_bindings.rs", "qnn_bindings.rs", "genie_bindings.rs"];
+
+/// SDK versions with bundled bindings, along with the feature selecting them.
+/// Without a version feature, bundled-bindings picks the last one.
+const BUNDLED_VERSIONS: [(&str, &str); 1] = [("CARGO_FEATURE_SDK_2_26", "2.26.0")];
 
 fn main() {
-    // let sdk_dir = setup_snapdragon_sdk();
+    if let Some(version) = bundled_version() {
+        use_bundled_bindings(version);
+        return;
+    }
+
+    #[cfg(feature = "download-sdk")]
+    let sdk_dir = download::setup_snapdragon_sdk();
+    #[cfg(not(feature = "download-sdk"))]
     let sdk_dir = PathBuf::from(std::env::var("SNPE_ROOT").unwrap_or("/snpe".to_string()));
-    setup_env_variables(&sdk_dir).expect("Failed to set environment variables");
-    let _platform_dir = linklibs(&sdk_dir);
+    let target = env::var("TARGET").unwrap();
+    let sdk = SdkLayout::detect(&sdk_dir, &target).unwrap_or_else(|message| panic!("{}", message));
+    // Nothing is linked, the bindings load the libraries at runtime
+    sdk.emit();
+
+    generate_genie_bindings(&sdk.include_dir);
+    generate_qnn_bindings(&sdk.include_dir);
+    generate_snpe_bindings(&sdk.include_dir).expect("Failed to bind SNPE library");
 
-    let include_dir = sdk_dir.join("include");
-    generate_genie_bindings(&include_dir);
-    generate_qnnbackend_bindings(&include_dir);
-    generate_snpe_bindings(&include_dir).expect("Failed to bind SNPE library");
+    if env::var_os("SNPE_UPDATE_BUNDLED").is_some() {
+        update_bundled_bindings(&sdk.version).expect("Failed to update the bundled bindings");
+    }
 }
 
-/// Downloads and unzips the Qualcomm Snapdragon SDK, returning the path to the sdk
-fn setup_snapdragon_sdk() -> PathBuf {
-    let url = "https://softwarecenter.qualcomm.com/api/download/software/qualcomm_neural_processing_sdk/v2.26.0.240828.zip";
-
-    // Download the qualcomm sdk to the current directory as "snapdragon_sdk.zip"
-    let project_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
-    let download_path = PathBuf::from(project_dir.clone()).join("snapdragon_sdk.zip");
-    let unzipped_path = PathBuf::from(project_dir.clone()).join("snapdragon_sdk");
-
-    if !unzipped_path.exists() {
-        // Download the zip file if necessary
-        if !download_path.exists() {
-            print!("Downloading snapdragon sdk...");
-            let mut zipfile = std::fs::File::create(&download_path).unwrap();
-            let mut response = reqwest::blocking::get(url).unwrap();
-            response
-                .copy_to(&mut zipfile)
-                .expect("Failed to download snapdragon sdk");
+/// Returns the sdk version whose bundled bindings were asked for, if any
+fn bundled_version() -> Option<&'static str> {
+    let selected = BUNDLED_VERSIONS
+        .iter()
+        .find(|(feature, _)| env::var_os(feature).is_some());
+
+    match selected {
+        Some((_, version)) => Some(version),
+        None if env::var_os("CARGO_FEATURE_BUNDLED_BINDINGS").is_some() => {
+            BUNDLED_VERSIONS.last().map(|(_, version)| *version)
         }
+        None => None,
+    }
+}
 
-        // Unzip the downloaded file
-        println!("Extracting snapdragon sdk...");
-        let zipfile = std::fs::File::open(&download_path).unwrap();
-        let unzipper = Unzipper::new(zipfile, &unzipped_path);
-        unzipper.unzip().expect("Failed to unzip snapdragon sdk");
+/// Directory holding the bundled bindings of an sdk version
+fn bundled_dir(version: &str) -> PathBuf {
+    PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap())
+        .join("bindings")
+        .join(version)
+}
+
+/// Copies the checked in bindings into OUT_DIR instead of generating them,
+/// so no sdk is needed to build. The libraries are found at runtime.
+fn use_bundled_bindings(version: &str) {
+    let target = env::var("TARGET").unwrap();
+    let platform_dir = platform_dir(&target);
+    println!("cargo:rustc-env=SNPE_PLATFORM_DIR={}", platform_dir);
+    println!("cargo:rustc-env=SNPE_HEADER_VERSION={}", version);
+
+    // Without an sdk there's no library to fall back on at runtime
+    let lib_dir = env::var("SNPE_ROOT")
+        .map(|root| PathBuf::from(root).join("lib").join(platform_dir))
+        .ok()
+        .filter(|dir| dir.is_dir());
+    println!(
+        "cargo:rustc-env=SNPE_LIB_DIR={}",
+        lib_dir
+            .as_ref()
+            .map_or(String::new(), |dir| dir.display().to_string())
+    );
 
-        // Delete the zip file
-        std::fs::remove_file(&download_path).unwrap();
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=SNPE_ROOT");
+
+    let bundled_dir = bundled_dir(version);
+    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
+    for file in BINDINGS {
+        let source = bundled_dir.join(file);
+        println!("cargo:rerun-if-changed={}", source.display());
+
+        if let Err(e) = fs::copy(&source, out_path.join(file)) {
+            panic!(
+                "Missing bundled bindings {} ({}). Generate them by building with an SDK {} and SNPE_UPDATE_BUNDLED=1",
+                source.display(),
+                e,
+                version
+            );
+        }
     }
+}
 
-    // Walk the directory and find the include and lib directories. The structure looks like qairt/<version>/. We need the version number
-    let sdk_dir = unzipped_path.join("qairt");
-    let entry = sdk_dir
-        .read_dir()
-        .unwrap()
-        .next()
-        .expect("Empty sdk directory");
-    let entry = entry.unwrap();
-    let version = entry
-        .file_name()
-        .to_str()
-        .expect("Failed to read version number")
-        .to_string();
-
-    println!("Using Qualcomm Snapdragon SDK v{}", version);
-    let sdk_dir = sdk_dir.join(version);
-
-    sdk_dir
+/// Copies freshly generated bindings into bindings/<version> to be checked in
+fn update_bundled_bindings(version: &str) -> Result<(), io::Error> {
+    let bundled_dir = bundled_dir(version);
+    fs::create_dir_all(&bundled_dir)?;
+
+    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
+    for file in BINDINGS {
+        fs::copy(out_path.join(file), bundled_dir.join(file))?;
+    }
+
+    println!(
+        "cargo:warning=Updated the bundled bindings in {}",
+        bundled_dir.display()
+    );
+    Ok(())
 }
 
-fn setup_env_variables(sdk_dir: &PathBuf) -> Result<(), io::Error> {
-    let output = std::process::Command::new("./scripts/printenv.sh")
-        .arg(sdk_dir.to_str().unwrap())
-        .output()?;
-
-    if !output.status.success() {
-        return Err(io::Error::new(
-            io::ErrorKind::Other,
-            format!(
-                "Failed to run printenv.sh: {}",
-                String::from_utf8_lossy(&output.stderr)
+/// Fetches the sdk when building with download-sdk, instead of expecting one
+/// at SNPE_ROOT
+#[cfg(feature = "download-sdk")]
+mod download {
+    extern crate reqwest;
+    extern crate sha2;
+    extern crate unzip;
+
+    use sha2::{Digest, Sha256};
+    use std::fs::{self, File};
+    use std::io;
+    use std::path::{Path, PathBuf};
+    use std::{env, process};
+    use unzip::Unzipper;
+
+    /// Returns the sdk in the archive at SNPE_SDK_ZIP, which is a url or a local
+    /// zip. Both it and SNPE_SDK_SHA256 are required, and the archive has to
+    /// match the checksum. Its qairt/<version> directories are extracted into a
+    /// cache shared between builds, so each archive is only fetched once.
+    pub fn setup_snapdragon_sdk() -> PathBuf {
+        for var in [
+            "SNPE_SDK_ZIP",
+            "SNPE_SDK_SHA256",
+            "SNPE_SDK_VERSION",
+            "SNPE_SDK_CACHE",
+        ] {
+            println!("cargo:rerun-if-env-changed={}", var);
+        }
+
+        let source = env::var("SNPE_SDK_ZIP").expect(
+            "Set SNPE_SDK_ZIP to the url or path of the sdk archive to build with download-sdk",
+        );
+        let checksum = env::var("SNPE_SDK_SHA256")
+            .ok()
+            .map(|checksum| checksum.trim().to_lowercase());
+        let cache_dir = cache_dir();
+
+        let cached = checksum
+            .as_ref()
+            .and_then(|checksum| cached_versions(&cache_dir, checksum));
+        let versions = match cached {
+            Some(versions) => versions,
+            None => {
+                let (archive, downloaded) = fetch(&source, &cache_dir);
+                let digest = verify(&archive, checksum.as_deref(), downloaded);
+                let versions = extract(&archive, &cache_dir, &digest);
+
+                if downloaded {
+                    fs::remove_file(&archive).unwrap();
+                }
+                versions
+            }
+        };
+
+        let version = select_version(&versions);
+        println!("Using Qualcomm Snapdragon SDK v{}", version);
+        cache_dir.join(version)
+    }
+
+    /// Directory the sdks are extracted into, SNPE_SDK_CACHE or the user's cache
+    fn cache_dir() -> PathBuf {
+        if let Some(dir) = env::var_os("SNPE_SDK_CACHE") {
+            return PathBuf::from(dir);
+        }
+
+        env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+            .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))
+            .unwrap_or_else(env::temp_dir)
+            .join("snpe-rust")
+    }
+
+    /// Each extracted archive leaves a record named after its checksum, listing
+    /// the versions it held
+    fn record_path(cache_dir: &Path, digest: &str) -> PathBuf {
+        cache_dir.join("archives").join(digest)
+    }
+
+    /// Returns the versions of an archive that was already extracted, as long
+    /// as they're all still there
+    fn cached_versions(cache_dir: &Path, digest: &str) -> Option<Vec<String>> {
+        let record = fs::read_to_string(record_path(cache_dir, digest)).ok()?;
+        let versions: Vec<String> = record.lines().map(str::to_string).collect();
+
+        let complete = !versions.is_empty()
+            && versions
+                .iter()
+                .all(|version| cache_dir.join(version).is_dir());
+        complete.then_some(versions)
+    }
+
+    /// Returns the path to the archive, downloading it into the cache when the
+    /// source is a url, along with whether it was downloaded
+    fn fetch(source: &str, cache_dir: &Path) -> (PathBuf, bool) {
+        if !source.starts_with("http://") && !source.starts_with("https://") {
+            println!("cargo:rerun-if-changed={}", source);
+            return (PathBuf::from(source), false);
+        }
+
+        let downloads = cache_dir.join("downloads");
+        fs::create_dir_all(&downloads).unwrap();
+        let name = source
+            .rsplit('/')
+            .next()
+            .filter(|name| !name.is_empty())
+            .unwrap_or("snapdragon_sdk.zip");
+        let download_path = downloads.join(name);
+
+        // Left by an earlier build that stopped before extracting it, e.g. for
+        // lack of a checksum. A corrupt one fails verification and is removed.
+        if download_path.is_file() {
+            return (download_path, true);
+        }
+
+        // Written under another name first, so an interrupted download isn't
+        // mistaken for the archive
+        let partial_path = download_path.with_extension(format!("part{}", process::id()));
+        println!("Downloading snapdragon sdk from {}...", source);
+        let mut zipfile = File::create(&partial_path).unwrap();
+        reqwest::blocking::get(source)
+            .and_then(|response| response.error_for_status())
+            .and_then(|mut response| response.copy_to(&mut zipfile))
+            .expect("Failed to download snapdragon sdk");
+        fs::rename(&partial_path, &download_path).unwrap();
+
+        (download_path, true)
+    }
+
+    /// Checks the archive against the expected checksum, returning its digest.
+    /// A downloaded archive that doesn't match is removed, so the next build
+    /// fetches it again.
+    fn verify(archive: &Path, expected: Option<&str>, downloaded: bool) -> String {
+        let mut file = File::open(archive)
+            .unwrap_or_else(|e| panic!("Failed to open sdk archive {}: {}", archive.display(), e));
+        let mut hasher = Sha256::new();
+        io::copy(&mut file, &mut hasher).expect("Failed to read sdk archive");
+        let digest = format!("{:x}", hasher.finalize());
+
+        match expected {
+            Some(expected) if expected == digest => digest,
+            Some(expected) => {
+                if downloaded {
+                    fs::remove_file(archive).unwrap();
+                }
+                panic!(
+                    "Checksum mismatch for sdk archive {}: expected {}, got {}",
+                    archive.display(),
+                    expected,
+                    digest
+                )
+            }
+            None => panic!(
+                "Set SNPE_SDK_SHA256 to the checksum of the sdk archive {}. If it's the archive you expect, that's {}",
+                archive.display(),
+                digest
             ),
-        ));
+        }
     }
 
-    // Capture the output
-    let output_str = String::from_utf8_lossy(&output.stdout);
-    let desired_variables = vec!["PYTHONPATH", "LD_LIBRARY_PATH", "SNPE_ROOT", "PATH"];
+    /// Unzips the archive and moves its qairt/<version> directories into the
+    /// cache, returning the versions
+    fn extract(archive: &Path, cache_dir: &Path, digest: &str) -> Vec<String> {
+        println!("Extracting snapdragon sdk...");
+        let staging = cache_dir.join(format!(".extract-{}", process::id()));
+        let zipfile = File::open(archive).unwrap();
+        Unzipper::new(zipfile, &staging)
+            .unzip()
+            .expect("Failed to unzip snapdragon sdk");
+
+        let entries = staging
+            .join("qairt")
+            .read_dir()
+            .expect("No qairt directory in the sdk archive");
+
+        let mut versions = vec![];
+        for entry in entries {
+            let entry = entry.unwrap();
+            let version = entry
+                .file_name()
+                .to_str()
+                .expect("Failed to read version number")
+                .to_string();
+
+            // Another build may have extracted the same version in the meantime
+            let target = cache_dir.join(&version);
+            if !target.exists() {
+                if let Err(e) = fs::rename(entry.path(), &target) {
+                    assert!(target.exists(), "Failed to cache sdk {}: {}", version, e);
+                }
+            }
+            versions.push(version);
+        }
+        fs::remove_dir_all(&staging).unwrap();
+        assert!(!versions.is_empty(), "Empty sdk directory");
 
-    // Parse and set environment variables
-    for line in output_str.lines() {
-        if let Some((key, value)) = line.split_once('=') {
-            if desired_variables.contains(&key) {
-                env::set_var(key, value);
-                println!("cargo:rustc-env={}={}", key, value);
+        versions.sort();
+        let record = record_path(cache_dir, digest);
+        fs::create_dir_all(record.parent().unwrap()).unwrap();
+        fs::write(record, versions.join("\n")).unwrap();
+
+        versions
+    }
+
+    /// Picks the sdk named by SNPE_SDK_VERSION, either fully (2.26.0.240828) or
+    /// by a prefix (2.26.0), and the only one otherwise
+    fn select_version(versions: &[String]) -> &str {
+        let Ok(wanted) = env::var("SNPE_SDK_VERSION") else {
+            return match versions {
+                [version] => version,
+                _ => panic!(
+                    "The sdk archive holds several versions ({}), pick one with SNPE_SDK_VERSION",
+                    versions.join(", ")
+                ),
+            };
+        };
+
+        let prefix = format!("{}.", wanted);
+        versions
+            .iter()
+            .rfind(|version| **version == wanted || version.starts_with(&prefix))
+            .unwrap_or_else(|| {
+                panic!(
+                    "SNPE_SDK_VERSION is {}, but the sdk archive only holds {}",
+                    wanted,
+                    versions.join(", ")
+                )
+            })
+    }
+}
+
+/// Headers the bindings are generated from, relative to the include directory
+const REQUIRED_HEADERS: [&str; 8] = [
+    "SNPE/SNPE/SNPE.h",
+    "SNPE/SNPE/SNPEBuilder.h",
+    "SNPE/SNPE/SNPEUtil.h",
+    "SNPE/SNPE/PSNPE.h",
+    "SNPE/DlContainer/DlContainer.h",
+    "QNN/QnnInterface.h",
+    "QNN/System/QnnSystemInterface.h",
+    "Genie/GenieDialog.h",
+];
+
+/// Where the parts of an sdk are for the target, found from its directory
+/// layout rather than by sourcing envsetup.sh
+struct SdkLayout {
+    root: PathBuf,
+    include_dir: PathBuf,
+    lib_dir: PathBuf,
+    /// The tools, which the build doesn't need, so they may be missing
+    bin_dir: Option<PathBuf>,
+    platform_dir: &'static str,
+    version: String,
+}
+
+impl SdkLayout {
+    /// Checks the sdk at `dir` has everything the build needs, listing what's
+    /// missing otherwise. `dir` may also be the directory the sdk archive was
+    /// unzipped into, as long as it only holds one qairt/<version>.
+    fn detect(dir: &Path, target: &str) -> Result<Self, String> {
+        let root = sdk_root(dir)?;
+        let platform_dir = platform_dir(target);
+
+        let mut missing = vec![];
+        let include_dir = root.join("include");
+        for header in REQUIRED_HEADERS {
+            if !include_dir.join(header).is_file() {
+                missing.push(format!("include/{}", header));
             }
         }
+
+        let lib_dir = root.join("lib").join(platform_dir);
+        let library = match target.contains("windows") {
+            true => "SNPE.dll",
+            false => "libSNPE.so",
+        };
+        if !lib_dir.join(library).is_file() {
+            missing.push(format!("lib/{}/{}", platform_dir, library));
+        }
+
+        if !missing.is_empty() {
+            let mut message = format!(
+                "The SNPE sdk at {} doesn't have what's needed to build for {}. Missing:",
+                root.display(),
+                target
+            );
+            for path in missing {
+                message.push_str(&format!("\n  {}", path));
+            }
+
+            let platforms = subdirectories(&root.join("lib"));
+            if !lib_dir.is_dir() && !platforms.is_empty() {
+                message.push_str(&format!(
+                    "\nIts libraries are for {}, not {}",
+                    platforms.join(", "),
+                    platform_dir
+                ));
+            }
+            return Err(message);
+        }
+
+        let bin_dir = Some(root.join("bin").join(platform_dir)).filter(|dir| dir.is_dir());
+        let version = header_version(&root);
+
+        Ok(Self {
+            root,
+            include_dir,
+            lib_dir,
+            bin_dir,
+            platform_dir,
+            version,
+        })
     }
 
-    Ok(())
+    /// Passes the layout on to the crate
+    fn emit(&self) {
+        println!("cargo:rustc-env=SNPE_ROOT={}", self.root.display());
+        println!("cargo:rustc-env=SNPE_LIB_DIR={}", self.lib_dir.display());
+        println!(
+            "cargo:rustc-env=SNPE_BIN_DIR={}",
+            self.bin_dir
+                .as_ref()
+                .map_or(String::new(), |dir| dir.display().to_string())
+        );
+        println!("cargo:rustc-env=SNPE_PLATFORM_DIR={}", self.platform_dir);
+        println!("cargo:rustc-env=SNPE_HEADER_VERSION={}", self.version);
+
+        println!("cargo:rerun-if-changed=build.rs");
+        println!("cargo:rerun-if-env-changed=SNPE_ROOT");
+        println!(
+            "cargo:rerun-if-changed={}",
+            self.root.join("sdk.yaml").display()
+        );
+    }
 }
 
-fn linklibs(sdk_dir: &PathBuf) -> &str {
-    let target = env::var("TARGET").unwrap();
-    let platform_dir = match target.as_str() {
+/// Returns the root of the sdk in `dir`, the directory with include/ and lib/
+fn sdk_root(dir: &Path) -> Result<PathBuf, String> {
+    if !dir.is_dir() {
+        return Err(format!(
+            "No SNPE sdk at {}. Set SNPE_ROOT to the sdk's root directory, the one with include/ and lib/ in it",
+            dir.display()
+        ));
+    }
+    if dir.join("include").is_dir() {
+        return Ok(dir.to_path_buf());
+    }
+
+    let versions = subdirectories(&dir.join("qairt"));
+    match versions.as_slice() {
+        [version] => Ok(dir.join("qairt").join(version)),
+        [] => Err(format!(
+            "{} isn't an SNPE sdk, it has neither include/ nor qairt/<version> in it",
+            dir.display()
+        )),
+        _ => Err(format!(
+            "{} holds several sdks ({}), set SNPE_ROOT to one of them",
+            dir.display(),
+            versions.join(", ")
+        )),
+    }
+}
+
+/// Names of the directories in `dir`, sorted
+fn subdirectories(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = dir
+        .read_dir()
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    names.sort();
+    names
+}
+
+/// Returns the directory the sdk keeps the libraries for a target in
+fn platform_dir(target: &str) -> &'static str {
+    match target {
         // Windows on x86 and arm
         "x86_64-pc-windows-msvc" => "x86_64-windows-msvc",
         "aarch64-pc-windows-msvc" => "arm64x-windows-msvc",
@@ -125,52 +514,56 @@ fn linklibs(sdk_dir: &PathBuf) -> &str {
         "aarch64-unknown-linux-gnu" => "aarch64-ubuntu-gcc9.4",
 
         _ => panic!("Unsupported platform: {}", target),
-    };
-
-    let native_lib_dir = sdk_dir.join("lib").join(platform_dir);
-    println!("cargo:rustc-env=SNPE_LIB_DIR={}", native_lib_dir.display());
-
-    println!(
-        "cargo:rustc-link-search=all={}",
-        native_lib_dir.to_str().unwrap()
-    );
-
-    for lib in native_lib_dir.read_dir().unwrap() {
-        let lib = lib.unwrap();
-
-        if let Some(ext) = lib.path().extension() {
-            let name = library_name(&lib);
-            if ext == "so" || ext == "dll" {
-                println!("cargo:rustc-link-lib=dylib={}", name);
-            } else if ext == "a" || ext == "lib" {
-                println!("cargo:rustc-link-lib=static={}", name);
-            }
-        }
     }
-
-    platform_dir
 }
 
-/// Takes a path like /path/to/libLibrary.so and returns the library name (Library)
-fn library_name(entry: &DirEntry) -> String {
-    let path = entry.path();
-    let name = path.file_stem().unwrap().to_str().unwrap();
-    String::from(&name[3..])
+/// Finds the version of the sdk the bindings are generated from, as major.minor.patch.
+/// The sdk lists it in sdk.yaml, and its directory is usually named after it as well.
+fn header_version(sdk_dir: &Path) -> String {
+    let from_yaml = fs::read_to_string(sdk_dir.join("sdk.yaml"))
+        .ok()
+        .and_then(|yaml| {
+            yaml.lines()
+                .find_map(|line| line.trim().strip_prefix("version:"))
+                .map(|version| version.trim().trim_matches('"').to_string())
+        });
+
+    let from_dir = sdk_dir.canonicalize().ok().and_then(|dir| {
+        dir.file_name()
+            .map(|name| name.to_string_lossy().to_string())
+    });
+
+    let version = [from_yaml, from_dir]
+        .into_iter()
+        .flatten()
+        .find_map(|version| {
+            let parts: Vec<&str> = version.split('.').take(3).collect();
+            let numeric = parts.iter().all(|part| part.parse::<u64>().is_ok());
+            (parts.len() == 3 && numeric).then(|| parts.join("."))
+        });
+
+    match version {
+        Some(version) => version,
+        None => {
+            println!("cargo:warning=Could not find the SNPE sdk version, assuming 2.26.0");
+            "2.26.0".to_string()
+        }
+    }
 }
 
 /// Generates bindings to the C api
 fn generate_genie_bindings(include_dir: &PathBuf) {
     let header_include_dir = include_dir.join("Genie");
     let header_file = header_include_dir.join("GenieDialog.h");
-    assert!(header_file.exists());
 
     let include = header_include_dir.to_str().unwrap();
     let include_arg = format!("--include-directory={}/", include);
-    println!("{}", include_arg);
+    // libGenie is opened at runtime, so the crate still loads where it's missing
     let bindings = bindgen::Builder::default()
         .clang_arg(include_arg)
         .header(header_file.to_str().unwrap())
-        .raw_line("#[allow(warnings)]")
+        .dynamic_library_name("Genie")
+        .raw_line("#[allow(warnings, non_camel_case_types, non_snake_case)]")
         .generate()
         .expect("Unable to generate bindings");
 
@@ -180,34 +573,40 @@ fn generate_genie_bindings(include_dir: &PathBuf) {
         .expect("Couldn't write bindings for genie!");
 }
 
-fn generate_qnnbackend_bindings(include_dir: &PathBuf) {
-    let mut devices: HashMap<&str, &str> = HashMap::new();
-
-    devices.insert("cpu", "QnnCpu");
-    devices.insert("gpu", "QnnGpu");
-    devices.insert("htp", "QnnHtp");
-
-    for (device, lib) in devices {
-        let header_include_dir = include_dir.join("QNN");
-        let header_file = header_include_dir.join("QnnBackend.h");
-        assert!(header_file.exists());
-
-        let include = header_include_dir.to_str().unwrap();
-        let include_arg = format!("--include-directory={}/", include);
-        println!("{}", include_arg);
-        let bindings = bindgen::Builder::default()
-            .clang_arg(include_arg)
-            .header(header_file.to_str().unwrap())
-            .raw_line("#[allow(warnings, non_camel_case_types, non_snake_case)]")
-            .dynamic_library_name(lib)
-            .generate()
-            .expect("Unable to generate bindings");
-
-        let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
-        bindings
-            .write_to_file(out_path.join(format!("qnn{}_bindings.rs", device)))
-            .expect(format!("Couldn't write bindings for {}!", device).as_str());
-    }
+/// Names the function tables of the QNN interface providers. They're the
+/// only member of a union named after the api version, so the rust side
+/// reads them through these aliases instead.
+const QNN_WRAPPER: &str = r#"
+#include "QnnInterface.h"
+#include "System/QnnSystemInterface.h"
+
+typedef QNN_INTERFACE_VER_TYPE QnnInterface_Implementation_t;
+typedef QNN_SYSTEM_INTERFACE_VER_TYPE QnnSystemInterface_Implementation_t;
+"#;
+
+fn generate_qnn_bindings(include_dir: &PathBuf) {
+    let header_include_dir = include_dir.join("QNN");
+
+    let include = header_include_dir.to_str().unwrap();
+    let include_arg = format!("--include-directory={}/", include);
+
+    // Backend libraries are picked at runtime and only export the provider
+    // lookup, which is loaded by hand, so only the types are generated
+    let bindings = bindgen::Builder::default()
+        .clang_arg(include_arg)
+        // The headers declare their unions as `union UNNAMED { ... };`
+        .clang_arg("-fms-extensions")
+        .header_contents("QnnWrapper.h", QNN_WRAPPER)
+        .allowlist_type("Qnn.*")
+        .allowlist_var("QNN_.*")
+        .raw_line("#[allow(warnings, non_camel_case_types, non_snake_case)]")
+        .generate()
+        .expect("Unable to generate bindings");
+
+    let out_path = PathBuf::from(env::var("OUT_DIR").unwrap());
+    bindings
+        .write_to_file(out_path.join("qnn_bindings.rs"))
+        .expect("Couldn't write bindings for qnn!");
 }
 
 fn generate_snpe_bindings(include_dir: &PathBuf) -> Result<(), BindgenError> {
@@ -216,11 +615,12 @@ fn generate_snpe_bindings(include_dir: &PathBuf) -> Result<(), BindgenError> {
 
     let include = header_include_dir.to_str().unwrap();
     let include_arg = format!("--include-directory={}/", include);
-    println!("{}", include_arg);
     let builder = bindgen::Builder::default()
         .clang_arg(include_arg)
         .header(snpe_dir.join("SNPE.h").to_str().unwrap())
+        .header(snpe_dir.join("SNPEBuilder.h").to_str().unwrap())
         .header(snpe_dir.join("SNPEUtil.h").to_str().unwrap())
+        .header(snpe_dir.join("PSNPE.h").to_str().unwrap())
         .header(
             header_include_dir
                 .join("DlContainer")
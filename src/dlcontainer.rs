@@ -1,43 +1,40 @@
-mod errors;
+pub(crate) mod errors;
 use std::ffi::{CStr, CString};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use log::warn;
 
-use self::errors::DlContainerError;
+pub use self::errors::DlContainerError;
 use super::snpe::snpe_bindings;
+use crate::errors::{lossy_string, Error};
 
 /// Model instance for the SNPE runtime
 #[derive(Debug)]
-struct DlContainer {
+pub struct DlContainer {
     /// File path to the .dlc model file
     path: PathBuf,
 
     /// Internal handle to the c object
-    handle: snpe_bindings::Snpe_DlContainer_Handle_t,
+    pub(crate) handle: snpe_bindings::Snpe_DlContainer_Handle_t,
 }
 
 impl DlContainer {
     /// Creates a new DlContainer from the given path to a .dlc or .bin file
-    fn from_path<P>(path: P) -> Result<DlContainer, DlContainerError>
+    pub fn from_path<P>(path: P) -> Result<DlContainer, Error>
     where
         P: AsRef<str>,
     {
         let handle: snpe_bindings::Snpe_DlContainer_Handle_t;
-        let c_path = CString::new(path.as_ref()).unwrap();
+        let c_path = CString::new(path.as_ref())?;
         let model: DlContainer;
         let pathbuf = PathBuf::from(path.as_ref());
 
         unsafe {
-            let snpe = snpe_bindings::get();
+            let snpe = snpe_bindings::get()?;
             handle = snpe.Snpe_DlContainer_Open(c_path.as_ptr());
 
             if handle.is_null() {
-                let code = snpe.Snpe_ErrorCode_getLastErrorCode();
-                let msg = snpe.Snpe_ErrorCode_GetLastErrorString();
-                let msg_str = String::from(std::ffi::CStr::from_ptr(msg).to_str().unwrap());
-
-                return Err(DlContainerError::from_error(code, msg_str));
+                return Err(Error::last(&snpe));
             }
 
             model = DlContainer {
@@ -50,18 +47,12 @@ impl DlContainer {
     }
 
     /// Loads a DlContainer from an in-memory byte buffer
-    fn from_buffer(buffer: &[u8]) -> Result<DlContainer, DlContainerError> {
-        let snpe = unsafe { snpe_bindings::get() };
+    pub fn from_buffer(buffer: &[u8]) -> Result<DlContainer, Error> {
+        let snpe = unsafe { snpe_bindings::get()? };
         let handle = unsafe { snpe.Snpe_DlContainer_OpenBuffer(buffer.as_ptr(), buffer.len()) };
 
         if handle.is_null() {
-            unsafe {
-                let code = snpe.Snpe_ErrorCode_getLastErrorCode();
-                let msg = snpe.Snpe_ErrorCode_GetLastErrorString();
-                let msg_str = String::from(std::ffi::CStr::from_ptr(msg).to_str().unwrap());
-
-                return Err(DlContainerError::from_error(code, msg_str));
-            }
+            return Err(unsafe { Error::last(&snpe) });
         }
 
         Ok(DlContainer {
@@ -71,38 +62,36 @@ impl DlContainer {
     }
 
     /// Saves a DlContainer to a file
-    fn save<P>(&self, path: P) -> Result<(), DlContainerError>
+    pub fn save<P>(&self, path: P) -> Result<(), Error>
     where
         P: AsRef<str>,
     {
-        let c_path = CString::new(path.as_ref()).unwrap();
-        let snpe = unsafe { snpe_bindings::get() };
+        let c_path = CString::new(path.as_ref())?;
+        let snpe = unsafe { snpe_bindings::loaded() };
         let code = unsafe { snpe.Snpe_DlContainer_Save(self.handle, c_path.as_ptr()) };
         if code != 0 {
-            let msg = unsafe {
-                let code = snpe.Snpe_ErrorCode_getLastErrorCode();
-                let msg = snpe.Snpe_ErrorCode_GetLastErrorString();
-                String::from(std::ffi::CStr::from_ptr(msg).to_str().unwrap())
-            };
-            let msg_str = String::from(msg);
-            return Err(DlContainerError::from_error(code, msg_str));
+            return Err(unsafe { Error::last(&snpe) });
         }
 
         Ok(())
     }
 
+    /// Returns the path the container was loaded from, which is empty for
+    /// containers loaded from a buffer
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
     /// Returns a DlcRecord by name
-    fn get_record(&self, name: &str) -> Result<DlcRecord, DlContainerError> {
+    pub fn get_record(&self, name: &str) -> Result<DlcRecord, Error> {
+        let cname = CString::new(name)?;
+
         unsafe {
-            let snpe = snpe_bindings::get();
-            let cname = CString::new(name).unwrap();
+            let snpe = snpe_bindings::loaded();
             let handle = snpe.Snpe_DlContainer_GetRecord(self.handle, cname.as_ptr());
 
             if handle.is_null() {
-                let code = snpe.Snpe_ErrorCode_getLastErrorCode();
-                let msg = snpe.Snpe_ErrorCode_GetLastErrorString();
-                let msg_str = String::from(std::ffi::CStr::from_ptr(msg).to_str().unwrap());
-                return Err(DlContainerError::from_error(code, msg_str));
+                return Err(Error::last(&snpe));
             }
 
             Ok(DlcRecord::new(name, handle))
@@ -110,19 +99,29 @@ impl DlContainer {
     }
 
     /// Returns all records in this container
-    fn get_catalog(&self) -> Result<Vec<DlcRecord>, DlContainerError> {
-        let mut result = Vec::<DlcRecord>::new();
+    pub fn get_catalog(&self) -> Result<Vec<DlcRecord>, Error> {
+        let mut names = Vec::<String>::new();
 
         unsafe {
-            let snpe = snpe_bindings::get();
+            let snpe = snpe_bindings::loaded();
             let record_names_raw = snpe.Snpe_DlContainer_GetCatalog(self.handle);
-            let num_records = snpe.Snpe_StringList_Size(record_names_raw);
 
+            if record_names_raw.is_null() {
+                return Err(Error::last(&snpe));
+            }
+
+            let num_records = snpe.Snpe_StringList_Size(record_names_raw);
             for i in 0..num_records {
                 let record_name = CStr::from_ptr(snpe.Snpe_StringList_At(record_names_raw, i));
-                let record = self.get_record(&record_name.to_string_lossy().to_string())?;
-                result.push(record);
+                names.push(record_name.to_string_lossy().to_string());
             }
+
+            snpe.Snpe_StringList_Delete(record_names_raw);
+        }
+
+        let mut result = Vec::<DlcRecord>::new();
+        for name in names {
+            result.push(self.get_record(&name)?);
         }
 
         Ok(result)
@@ -133,21 +132,20 @@ impl Drop for DlContainer {
     fn drop(&mut self) {
         // Clean up the dlcontainer handle
         unsafe {
-            let snpe = snpe_bindings::get();
+            let snpe = snpe_bindings::loaded();
             let errorCode = snpe.Snpe_DlContainer_Delete(self.handle);
 
             // If there was an error, log it, but not sure what else we can do
             if errorCode != 0 {
-                let msg = snpe.Snpe_ErrorCode_GetLastErrorString();
-                let msg_str = String::from(std::ffi::CStr::from_ptr(msg).to_str().unwrap());
-                warn!(target: "DlContainer", "Error cleaning up container: {}", msg_str);
+                let error = Error::last(&snpe);
+                warn!(target: "DlContainer", "Error cleaning up container: {}", error);
             }
         }
     }
 }
 
 /// A record in the .dlc file
-struct DlcRecord {
+pub struct DlcRecord {
     name: String,
     handle: snpe_bindings::Snpe_DlcRecord_Handle_t,
 }
@@ -161,34 +159,47 @@ impl DlcRecord {
         }
     }
 
+    /// Returns the name of this record
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
     /// Creates a new DlcRecord and handle with the supplied name
-    fn create(name: Option<&str>) -> DlcRecord {
+    pub fn create(name: Option<&str>) -> Result<DlcRecord, Error> {
         unsafe {
-            let snpe = snpe_bindings::get();
+            let snpe = snpe_bindings::get()?;
 
             match name {
                 Some(name) => {
-                    let cname = CString::new(name).unwrap();
+                    let cname = CString::new(name)?;
                     let handle = snpe.Snpe_DlcRecord_CreateName(cname.as_ptr());
-                    DlcRecord::new(name, handle)
+                    if handle.is_null() {
+                        return Err(Error::last(&snpe));
+                    }
+
+                    Ok(DlcRecord::new(name, handle))
                 }
                 None => {
                     let handle = snpe.Snpe_DlcRecord_Create();
+                    if handle.is_null() {
+                        return Err(Error::last(&snpe));
+                    }
+
                     let cname = snpe.Snpe_DlcRecord_Name(handle);
-                    DlcRecord::new(std::ffi::CStr::from_ptr(cname).to_str().unwrap(), handle)
+                    Ok(DlcRecord::new(&lossy_string(cname), handle))
                 }
             }
         }
     }
 
     /// Returns a copy of the byte buffer of this record
-    fn get_data(&self) -> Result<Vec<u8>, &str> {
+    pub fn get_data(&self) -> Result<Vec<u8>, Error> {
         unsafe {
-            let snpe = snpe_bindings::get();
+            let snpe = snpe_bindings::loaded();
             let data_ptr = self.data_ptr();
 
             if data_ptr.is_null() {
-                return Err("Failed to get data");
+                return Err(Error::last(&snpe));
             }
 
             Ok(std::slice::from_raw_parts(data_ptr, self.size()).to_vec())
@@ -196,22 +207,22 @@ impl DlcRecord {
     }
 
     /// Returns the size in bytes of this record
-    fn size(&self) -> usize {
+    pub fn size(&self) -> usize {
         unsafe {
-            let snpe = snpe_bindings::get();
+            let snpe = snpe_bindings::loaded();
             snpe.Snpe_DlcRecord_Size(self.handle)
         }
     }
 
     /// Gives the underlying (read only) pointer to the data
     unsafe fn data_ptr(&self) -> *const u8 {
-        let snpe = snpe_bindings::get();
+        let snpe = snpe_bindings::loaded();
         snpe.Snpe_DlcRecord_Data(self.handle)
     }
 
     /// Gives the underlying mutable pointer to the data
     unsafe fn mut_data_ptr(&self) -> *mut u8 {
-        let snpe = snpe_bindings::get();
+        let snpe = snpe_bindings::loaded();
         snpe.Snpe_DlcRecord_Data(self.handle)
     }
 }
@@ -219,7 +230,7 @@ impl DlcRecord {
 impl Drop for DlcRecord {
     fn drop(&mut self) {
         unsafe {
-            let snpe = snpe_bindings::get();
+            let snpe = snpe_bindings::loaded();
             snpe.Snpe_DlcRecord_Delete(self.handle);
         }
     }
@@ -228,17 +239,30 @@ impl Drop for DlcRecord {
 #[cfg(test)]
 mod tests {
     use crate::dlcontainer::errors::DlContainerError;
+    use crate::errors::Error;
 
     #[test]
     fn does_not_exist() {
         let model = super::DlContainer::from_path("does_not_exist.dlc");
-        assert!(matches!(model, Err(DlContainerError::ReadFailure(_))))
+        assert!(matches!(
+            model,
+            Err(Error::DlContainer(DlContainerError::ReadFailure(_)))
+        ))
     }
 
     #[test]
     fn dummy_file() {
         let model = super::DlContainer::from_path("test/data/dummy.dlc");
-        assert!(matches!(model, Err(DlContainerError::ReadFailure(_))))
+        assert!(matches!(
+            model,
+            Err(Error::DlContainer(DlContainerError::ReadFailure(_)))
+        ))
+    }
+
+    #[test]
+    fn nul_in_path() {
+        let model = super::DlContainer::from_path("bad\0path.dlc");
+        assert!(matches!(model, Err(Error::InvalidString(_))))
     }
 
     #[test]
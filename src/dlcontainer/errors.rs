@@ -2,91 +2,137 @@ extern crate thiserror;
 use thiserror::Error;
 
 use super::snpe_bindings::*;
+use crate::errors::ErrorDetails;
 
 /// Class of errors possible during DL container loading
 #[derive(Debug, Error)]
 pub enum DlContainerError {
-    #[error("Model parsing failed")]
-    ModelParsingFailed(String),
+    #[error("Model parsing failed, {0}")]
+    ModelParsingFailed(ErrorDetails),
 
-    #[error("Unknown layer code")]
-    UnknownLayerCode(String),
+    #[error("Unknown layer code, {0}")]
+    UnknownLayerCode(ErrorDetails),
 
-    #[error("Missing layer parameter")]
-    MissingLayerParam(String),
+    #[error("Missing layer parameter, {0}")]
+    MissingLayerParam(ErrorDetails),
 
-    #[error("Layer parameter is not supported")]
-    LayerParamNotSupported(String),
+    #[error("Layer parameter is not supported, {0}")]
+    LayerParamNotSupported(ErrorDetails),
 
-    #[error("Layer parameter is invalid")]
-    LayerParamInvalid(String),
+    #[error("Layer parameter is invalid, {0}")]
+    LayerParamInvalid(ErrorDetails),
 
-    #[error("Tensor data is missing")]
-    TensorDataMissing(String),
+    #[error("Tensor data is missing, {0}")]
+    TensorDataMissing(ErrorDetails),
 
-    #[error("Model load failed")]
-    ModelLoadFailed(String),
+    #[error("Model load failed, {0}")]
+    ModelLoadFailed(ErrorDetails),
 
-    #[error("Missing records")]
-    MissingRecords(String),
+    #[error("Missing records, {0}")]
+    MissingRecords(ErrorDetails),
 
-    #[error("Invalid record")]
-    InvalidRecord(String),
+    #[error("Invalid record, {0}")]
+    InvalidRecord(ErrorDetails),
 
-    #[error("Write failure")]
-    WriteFailure(String),
+    #[error("Write failure, {0}")]
+    WriteFailure(ErrorDetails),
 
-    #[error("Read failure")]
-    ReadFailure(String),
+    #[error("Read failure, {0}")]
+    ReadFailure(ErrorDetails),
 
-    #[error("Bad container")]
-    BadContainer(String),
+    #[error("Bad container, {0}")]
+    BadContainer(ErrorDetails),
 
-    #[error("Bad DNN format version")]
-    BadDnnFormatVersion(String),
+    #[error("Bad DNN format version, {0}")]
+    BadDnnFormatVersion(ErrorDetails),
 
-    #[error("Unknown axis annotation")]
-    UnknownAxisAnnotation(String),
+    #[error("Unknown axis annotation, {0}")]
+    UnknownAxisAnnotation(ErrorDetails),
 
-    #[error("Unknown shuffle type")]
-    UnknownShuffleType(String),
+    #[error("Unknown shuffle type, {0}")]
+    UnknownShuffleType(ErrorDetails),
 
-    #[error("Temp file failure")]
-    TempFileFailure(String),
+    #[error("Temp file failure, {0}")]
+    TempFileFailure(ErrorDetails),
 
-    #[error("Unknown error")]
-    Unknown,
+    #[error("Unknown error, {0}")]
+    Unknown(ErrorDetails),
 }
 
 impl DlContainerError {
-    pub fn from_error(code: Snpe_ErrorCode_t, message: String) -> Self {
-        match code {
+    /// Picks the error matching the code of a DlContainer error
+    pub fn from_error(details: ErrorDetails) -> Self {
+        match details.code as Snpe_ErrorCode_t {
             Snpe_ErrorCode_t_SNPE_ERRORCODE_DLCONTAINER_MODEL_PARSING_FAILED => {
-                Self::ModelParsingFailed(message)
+                Self::ModelParsingFailed(details)
             }
             Snpe_ErrorCode_t_SNPE_ERRORCODE_DLCONTAINER_UNKNOWN_LAYER_CODE => {
-                Self::UnknownLayerCode(message)
+                Self::UnknownLayerCode(details)
             }
             Snpe_ErrorCode_t_SNPE_ERRORCODE_DLCONTAINER_MISSING_LAYER_PARAM => {
-                Self::MissingLayerParam(message)
+                Self::MissingLayerParam(details)
             }
-            Snpe_ErrorCode_t_SNPE_ERRORCODE_DLCONTAINER_READ_FAILURE => Self::ReadFailure(message),
+            Snpe_ErrorCode_t_SNPE_ERRORCODE_DLCONTAINER_LAYER_PARAM_NOT_SUPPORTED => {
+                Self::LayerParamNotSupported(details)
+            }
+            Snpe_ErrorCode_t_SNPE_ERRORCODE_DLCONTAINER_LAYER_PARAM_INVALID => {
+                Self::LayerParamInvalid(details)
+            }
+            Snpe_ErrorCode_t_SNPE_ERRORCODE_DLCONTAINER_TENSOR_DATA_MISSING => {
+                Self::TensorDataMissing(details)
+            }
+            Snpe_ErrorCode_t_SNPE_ERRORCODE_DLCONTAINER_MODEL_LOAD_FAILED => {
+                Self::ModelLoadFailed(details)
+            }
+            Snpe_ErrorCode_t_SNPE_ERRORCODE_DLCONTAINER_MISSING_RECORDS => {
+                Self::MissingRecords(details)
+            }
+            Snpe_ErrorCode_t_SNPE_ERRORCODE_DLCONTAINER_INVALID_RECORD => {
+                Self::InvalidRecord(details)
+            }
+            Snpe_ErrorCode_t_SNPE_ERRORCODE_DLCONTAINER_WRITE_FAILURE => {
+                Self::WriteFailure(details)
+            }
+            Snpe_ErrorCode_t_SNPE_ERRORCODE_DLCONTAINER_READ_FAILURE => Self::ReadFailure(details),
             Snpe_ErrorCode_t_SNPE_ERRORCODE_DLCONTAINER_BAD_CONTAINER => {
-                Self::BadContainer(message)
+                Self::BadContainer(details)
             }
             Snpe_ErrorCode_t_SNPE_ERRORCODE_DLCONTAINER_BAD_DNN_FORMAT_VERSION => {
-                Self::BadDnnFormatVersion(message)
+                Self::BadDnnFormatVersion(details)
             }
             Snpe_ErrorCode_t_SNPE_ERRORCODE_DLCONTAINER_UNKNOWN_AXIS_ANNOTATION => {
-                Self::UnknownAxisAnnotation(message)
+                Self::UnknownAxisAnnotation(details)
             }
             Snpe_ErrorCode_t_SNPE_ERRORCODE_DLCONTAINER_UNKNOWN_SHUFFLE_TYPE => {
-                Self::UnknownShuffleType(message)
+                Self::UnknownShuffleType(details)
             }
             Snpe_ErrorCode_t_SNPE_ERRORCODE_DLCONTAINER_TEMP_FILE_FAILURE => {
-                Self::TempFileFailure(message)
+                Self::TempFileFailure(details)
             }
-            _ => Self::Unknown,
+            _ => Self::Unknown(details),
+        }
+    }
+
+    /// Returns the code, message and info reported by the library
+    pub fn details(&self) -> &ErrorDetails {
+        match self {
+            Self::ModelParsingFailed(details)
+            | Self::UnknownLayerCode(details)
+            | Self::MissingLayerParam(details)
+            | Self::LayerParamNotSupported(details)
+            | Self::LayerParamInvalid(details)
+            | Self::TensorDataMissing(details)
+            | Self::ModelLoadFailed(details)
+            | Self::MissingRecords(details)
+            | Self::InvalidRecord(details)
+            | Self::WriteFailure(details)
+            | Self::ReadFailure(details)
+            | Self::BadContainer(details)
+            | Self::BadDnnFormatVersion(details)
+            | Self::UnknownAxisAnnotation(details)
+            | Self::UnknownShuffleType(details)
+            | Self::TempFileFailure(details)
+            | Self::Unknown(details) => details,
         }
     }
 }
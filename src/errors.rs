@@ -0,0 +1,262 @@
+extern crate thiserror;
+use std::ffi::{CStr, NulError};
+use std::fmt;
+use std::os::raw::c_char;
+use std::str::Utf8Error;
+
+use semver::Version;
+use thiserror::Error;
+
+use crate::dlcontainer::errors::DlContainerError;
+use crate::snpe::snpe_bindings;
+
+/// Details of an error reported by the SNPE library
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorDetails {
+    /// Numeric Snpe_ErrorCode_t value
+    pub code: u32,
+    /// Message from Snpe_ErrorCode_GetLastErrorString
+    pub message: String,
+    /// Extra information from Snpe_ErrorCode_getLastInfo
+    pub info: String,
+}
+
+impl fmt::Display for ErrorDetails {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "code {}: {}", self.code, self.message)?;
+        if !self.info.is_empty() {
+            write!(f, " ({})", self.info)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Errors returned by this crate
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("General error, {0}")]
+    General(ErrorDetails),
+
+    #[error("C API error, {0}")]
+    Capi(ErrorDetails),
+
+    #[error("Configuration error, {0}")]
+    Config(ErrorDetails),
+
+    #[error("DlSystem error, {0}")]
+    DlSystem(ErrorDetails),
+
+    #[error("DlContainer error: {0}")]
+    DlContainer(#[from] DlContainerError),
+
+    #[error("Network error, {0}")]
+    Network(ErrorDetails),
+
+    #[error("Runtime error, {0}")]
+    Runtime(ErrorDetails),
+
+    #[error("Model validation error, {0}")]
+    ModelValidation(ErrorDetails),
+
+    #[error("UDL error, {0}")]
+    Udl(ErrorDetails),
+
+    #[error("UDO error, {0}")]
+    Udo(ErrorDetails),
+
+    #[error("Dependent library error, {0}")]
+    Library(ErrorDetails),
+
+    #[error("Storage error, {0}")]
+    Storage(ErrorDetails),
+
+    #[error("Caching error, {0}")]
+    DlCaching(ErrorDetails),
+
+    #[error("Infrastructure error, {0}")]
+    Infrastructure(ErrorDetails),
+
+    #[error("Memory error, {0}")]
+    Memory(ErrorDetails),
+
+    #[error("Unknown error, {0}")]
+    Unknown(ErrorDetails),
+
+    #[error("Invalid argument: {0}")]
+    InvalidArgument(String),
+
+    #[error("String contains a nul byte")]
+    InvalidString(#[from] NulError),
+
+    #[error("String is not valid utf-8")]
+    InvalidUtf8(#[from] Utf8Error),
+
+    #[error("Tensor shape mismatch: {0}")]
+    ShapeMismatch(String),
+
+    #[error("Unsupported element type: {0}")]
+    UnsupportedElementType(String),
+
+    #[error("Unsupported model format: {0}")]
+    UnsupportedFormat(String),
+
+    #[error("Invalid state: {0}")]
+    InvalidState(String),
+
+    #[error("Inference failed: {0}")]
+    ExecuteFailed(String),
+
+    #[error("QNN error, {0}")]
+    Qnn(ErrorDetails),
+
+    #[error("Genie error, {0}")]
+    Genie(ErrorDetails),
+
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to load library: {0}")]
+    LoadLibrary(#[from] libloading::Error),
+
+    #[error("Library not found, searched {0}")]
+    LibraryNotFound(String),
+
+    #[error(
+        "Library version {found} is incompatible with the bindings, generated from {expected}"
+    )]
+    IncompatibleVersion { expected: Version, found: Version },
+
+    #[cfg(feature = "tch")]
+    #[error("Torch error: {0}")]
+    Tch(#[from] tch::TchError),
+}
+
+impl Error {
+    /// Maps an SNPE error code to the family it belongs to. The families
+    /// follow the blocks of codes laid out in DlSystem/DlError.h.
+    pub fn from_code(code: snpe_bindings::Snpe_ErrorCode_t, message: String, info: String) -> Self {
+        let details = ErrorDetails {
+            code: code as u32,
+            message,
+            info,
+        };
+
+        match details.code {
+            0..=9 => Error::General(details),
+            10..=99 => Error::Capi(details),
+            100..=199 => Error::Config(details),
+            200..=299 => Error::DlSystem(details),
+            300..=399 => Error::DlContainer(DlContainerError::from_error(details)),
+            400..=499 => Error::Network(details),
+            // Host, cpu, gpu and dsp runtimes
+            500..=899 => Error::Runtime(details),
+            900..=999 => Error::ModelValidation(details),
+            1000..=1099 => Error::Udl(details),
+            1100..=1299 => Error::Library(details),
+            1300..=1399 => Error::Storage(details),
+            // Aip runtime
+            1400..=1499 => Error::Runtime(details),
+            1500..=1599 => Error::DlCaching(details),
+            1600..=1699 => Error::Infrastructure(details),
+            1700..=1799 => Error::Memory(details),
+            1800..=1899 => Error::Udo(details),
+            _ => Error::Unknown(details),
+        }
+    }
+
+    /// Reads the last error reported by the SNPE library
+    pub(crate) unsafe fn last(snpe: &snpe_bindings::SNPE) -> Self {
+        let code = snpe.Snpe_ErrorCode_getLastErrorCode();
+        let message = lossy_string(snpe.Snpe_ErrorCode_GetLastErrorString());
+        let info = lossy_string(snpe.Snpe_ErrorCode_getLastInfo());
+
+        Self::from_code(code, message, info)
+    }
+
+    /// Returns the SNPE error details, if the error came from the library
+    pub fn details(&self) -> Option<&ErrorDetails> {
+        match self {
+            Error::General(details)
+            | Error::Capi(details)
+            | Error::Config(details)
+            | Error::DlSystem(details)
+            | Error::Network(details)
+            | Error::Runtime(details)
+            | Error::ModelValidation(details)
+            | Error::Udl(details)
+            | Error::Udo(details)
+            | Error::Library(details)
+            | Error::Storage(details)
+            | Error::DlCaching(details)
+            | Error::Infrastructure(details)
+            | Error::Memory(details)
+            | Error::Unknown(details) => Some(details),
+            Error::DlContainer(error) => Some(error.details()),
+            _ => None,
+        }
+    }
+}
+
+/// Copies a possibly null c string into rust, replacing invalid utf-8
+pub(crate) unsafe fn lossy_string(ptr: *const c_char) -> String {
+    if ptr.is_null() {
+        return String::new();
+    }
+
+    CStr::from_ptr(ptr).to_string_lossy().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, ErrorDetails};
+    use crate::dlcontainer::errors::DlContainerError;
+    use crate::snpe::snpe_bindings::*;
+
+    #[test]
+    fn dlcontainer_codes() {
+        let error = Error::from_code(
+            Snpe_ErrorCode_t_SNPE_ERRORCODE_DLCONTAINER_READ_FAILURE,
+            "Failed to read".to_string(),
+            String::new(),
+        );
+        assert!(matches!(
+            error,
+            Error::DlContainer(DlContainerError::ReadFailure(_))
+        ));
+
+        let details = error.details().unwrap();
+        assert_eq!(
+            details.code,
+            Snpe_ErrorCode_t_SNPE_ERRORCODE_DLCONTAINER_READ_FAILURE as u32
+        );
+        assert_eq!(details.message, "Failed to read");
+    }
+
+    #[test]
+    fn capi_codes() {
+        let error = Error::from_code(
+            Snpe_ErrorCode_t_SNPE_ERRORCODE_CAPI_BAD_ARGUMENT,
+            "Bad argument".to_string(),
+            "handle was null".to_string(),
+        );
+        assert!(matches!(error, Error::Capi(_)));
+
+        let details = error.details().unwrap();
+        assert_eq!(details.message, "Bad argument");
+        assert_eq!(details.info, "handle was null");
+    }
+
+    #[test]
+    fn display_includes_details() {
+        let error = Error::Runtime(ErrorDetails {
+            code: 800,
+            message: "Layer not supported".to_string(),
+            info: String::new(),
+        });
+        assert_eq!(
+            error.to_string(),
+            "Runtime error, code 800: Layer not supported"
+        );
+    }
+}
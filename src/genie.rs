@@ -0,0 +1,120 @@
+/// Module for Genie, the sdk's runtime for large language models
+mod dialog;
+
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use libloading::Library;
+
+pub use self::dialog::{GenieDialog, GenieDialogConfig, QueryChunk, QueryStream, SentenceCode};
+use crate::errors::{Error, ErrorDetails};
+
+pub mod genie_bindings {
+    include!(concat!(env!("OUT_DIR"), "/genie_bindings.rs"));
+}
+
+/// File name of the Genie library on this platform
+#[cfg(windows)]
+const LIB_NAME: &str = "Genie.dll";
+#[cfg(not(windows))]
+const LIB_NAME: &str = "libGenie.so";
+
+/// The Genie library, loaded at runtime. Clones share the loaded library.
+#[derive(Clone)]
+pub struct GenieLibrary {
+    inner: Arc<LibraryInner>,
+}
+
+/// GenieDialog_signal, which only newer Genie libraries export
+pub(crate) type SignalFn = unsafe extern "C" fn(
+    genie_bindings::GenieDialog_Handle_t,
+    u32,
+) -> genie_bindings::Genie_Status_t;
+
+struct LibraryInner {
+    path: PathBuf,
+    genie: genie_bindings::Genie,
+    signal: Option<SignalFn>,
+    /// Keeps signal loaded. The library is only mapped once, this just holds
+    /// another reference to it.
+    _library: Library,
+}
+
+impl GenieLibrary {
+    /// Loads the library from the library search path
+    pub fn from_env() -> Result<Self, Error> {
+        Self::open(LIB_NAME)
+    }
+
+    /// Loads the library, given either a path or a file name on the library
+    /// search path
+    pub fn open<P: AsRef<OsStr>>(path: P) -> Result<Self, Error> {
+        let genie = unsafe { genie_bindings::Genie::new(path.as_ref())? };
+
+        // Looked up by name, since the headers bindings are generated from
+        // may predate it
+        let library = unsafe { Library::new(path.as_ref())? };
+        let signal = unsafe { library.get::<SignalFn>(b"GenieDialog_signal\0") }
+            .ok()
+            .map(|signal| *signal);
+
+        Ok(Self {
+            inner: Arc::new(LibraryInner {
+                path: PathBuf::from(path.as_ref()),
+                genie,
+                signal,
+                _library: library,
+            }),
+        })
+    }
+
+    /// Returns the path the library was loaded from
+    pub fn path(&self) -> &Path {
+        &self.inner.path
+    }
+
+    /// Returns the functions of the library
+    pub(crate) fn bindings(&self) -> &genie_bindings::Genie {
+        &self.inner.genie
+    }
+
+    /// Returns GenieDialog_signal, if the library has it
+    pub(crate) fn signal(&self) -> Option<SignalFn> {
+        self.inner.signal
+    }
+}
+
+/// Turns the status returned by a Genie call into an error naming the call
+fn check(status: genie_bindings::Genie_Status_t, call: &str) -> Result<(), Error> {
+    // Positive statuses are warnings, such as a query that was aborted
+    if status >= 0 {
+        return Ok(());
+    }
+
+    Err(Error::Genie(ErrorDetails {
+        code: status.unsigned_abs(),
+        message: format!("{} failed with status {}", call, status),
+        info: String::new(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check;
+    use crate::errors::Error;
+
+    #[test]
+    fn status_check() {
+        assert!(check(0, "GenieDialog_reset").is_ok());
+        assert!(check(1, "GenieDialog_query").is_ok());
+
+        match check(-2, "GenieDialog_create") {
+            Err(Error::Genie(details)) => {
+                assert_eq!(details.code, 2);
+                assert!(details.message.contains("GenieDialog_create"));
+            }
+            other => panic!("Expected a Genie error, got {:?}", other.err()),
+        }
+    }
+}
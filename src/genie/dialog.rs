@@ -0,0 +1,384 @@
+use std::any::Any;
+use std::ffi::{c_void, CString};
+use std::fs;
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::ptr;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use log::warn;
+
+use super::genie_bindings::{
+    GenieDialogConfig_Handle_t, GenieDialog_Handle_t, GenieDialog_SentenceCode_t,
+    GenieDialog_SentenceCode_t_GENIE_DIALOG_SENTENCE_ABORT,
+    GenieDialog_SentenceCode_t_GENIE_DIALOG_SENTENCE_BEGIN,
+    GenieDialog_SentenceCode_t_GENIE_DIALOG_SENTENCE_COMPLETE,
+    GenieDialog_SentenceCode_t_GENIE_DIALOG_SENTENCE_CONTINUE,
+    GenieDialog_SentenceCode_t_GENIE_DIALOG_SENTENCE_END,
+};
+use super::{check, GenieLibrary};
+use crate::errors::{lossy_string, Error};
+
+/// GenieDialog_Action_t of GenieDialog_signal that stops the query in progress
+const ACTION_ABORT: u32 = 1;
+
+/// Marks where a prompt or a response chunk sits in a sentence. Prompts can
+/// be sent in pieces with Begin, Continue and End, or whole with Complete.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SentenceCode {
+    Complete,
+    Begin,
+    Continue,
+    End,
+    Abort,
+}
+
+impl SentenceCode {
+    fn to_raw(self) -> GenieDialog_SentenceCode_t {
+        match self {
+            SentenceCode::Complete => GenieDialog_SentenceCode_t_GENIE_DIALOG_SENTENCE_COMPLETE,
+            SentenceCode::Begin => GenieDialog_SentenceCode_t_GENIE_DIALOG_SENTENCE_BEGIN,
+            SentenceCode::Continue => GenieDialog_SentenceCode_t_GENIE_DIALOG_SENTENCE_CONTINUE,
+            SentenceCode::End => GenieDialog_SentenceCode_t_GENIE_DIALOG_SENTENCE_END,
+            SentenceCode::Abort => GenieDialog_SentenceCode_t_GENIE_DIALOG_SENTENCE_ABORT,
+        }
+    }
+
+    fn from_raw(code: GenieDialog_SentenceCode_t) -> Option<Self> {
+        match code {
+            GenieDialog_SentenceCode_t_GENIE_DIALOG_SENTENCE_COMPLETE => {
+                Some(SentenceCode::Complete)
+            }
+            GenieDialog_SentenceCode_t_GENIE_DIALOG_SENTENCE_BEGIN => Some(SentenceCode::Begin),
+            GenieDialog_SentenceCode_t_GENIE_DIALOG_SENTENCE_CONTINUE => {
+                Some(SentenceCode::Continue)
+            }
+            GenieDialog_SentenceCode_t_GENIE_DIALOG_SENTENCE_END => Some(SentenceCode::End),
+            GenieDialog_SentenceCode_t_GENIE_DIALOG_SENTENCE_ABORT => Some(SentenceCode::Abort),
+            _ => None,
+        }
+    }
+}
+
+/// Piece of text generated in response to a query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryChunk {
+    pub text: String,
+    pub sentence_code: SentenceCode,
+}
+
+/// Dialog configuration, parsed from the json format used by genie-t2t-run
+pub struct GenieDialogConfig {
+    handle: GenieDialogConfig_Handle_t,
+    library: GenieLibrary,
+}
+
+impl GenieDialogConfig {
+    /// Parses a configuration, loading the Genie library from the library
+    /// search path
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        Self::with_library(&GenieLibrary::from_env()?, json)
+    }
+
+    /// Reads and parses a configuration file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// Parses a configuration using an already loaded Genie library
+    pub fn with_library(library: &GenieLibrary, json: &str) -> Result<Self, Error> {
+        let json = CString::new(json)?;
+
+        let mut handle = ptr::null();
+        unsafe {
+            check(
+                library
+                    .bindings()
+                    .GenieDialogConfig_createFromJson(json.as_ptr(), &mut handle),
+                "GenieDialogConfig_createFromJson",
+            )?;
+        }
+
+        Ok(Self {
+            handle,
+            library: library.clone(),
+        })
+    }
+
+    /// Returns the library the configuration was parsed with
+    pub fn library(&self) -> &GenieLibrary {
+        &self.library
+    }
+}
+
+impl Drop for GenieDialogConfig {
+    fn drop(&mut self) {
+        unsafe {
+            let status = self.library.bindings().GenieDialogConfig_free(self.handle);
+            if let Err(e) = check(status, "GenieDialogConfig_free") {
+                warn!(target: "GenieDialogConfig", "Failed to free config: {}", e);
+            }
+        }
+    }
+}
+
+/// Conversation with a language model. Generation is blocking, so a dialog
+/// runs one query at a time.
+pub struct GenieDialog {
+    inner: Arc<Mutex<DialogHandle>>,
+}
+
+struct DialogHandle {
+    handle: GenieDialog_Handle_t,
+    library: GenieLibrary,
+}
+
+// Genie dialogs aren't tied to the thread that created them, and the mutex
+// keeps queries from overlapping
+unsafe impl Send for DialogHandle {}
+
+impl GenieDialog {
+    /// Creates a dialog, loading the models named in the configuration. The
+    /// configuration can be dropped once the dialog exists.
+    pub fn new(config: &GenieDialogConfig) -> Result<Self, Error> {
+        let library = config.library().clone();
+
+        let mut handle = ptr::null();
+        unsafe {
+            check(
+                library
+                    .bindings()
+                    .GenieDialog_create(config.handle, &mut handle),
+                "GenieDialog_create",
+            )?;
+        }
+
+        Ok(Self {
+            inner: Arc::new(Mutex::new(DialogHandle { handle, library })),
+        })
+    }
+
+    /// Sends a prompt, calling back with each chunk of the response as it's
+    /// generated. Returns once generation is finished.
+    pub fn query<F>(
+        &mut self,
+        prompt: &str,
+        sentence_code: SentenceCode,
+        callback: F,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(&str, SentenceCode),
+    {
+        let prompt = CString::new(prompt)?;
+        let mut callback = callback;
+
+        lock(&self.inner).query(&prompt, sentence_code, &mut callback)
+    }
+
+    /// Sends a prompt and returns the chunks of the response as they're
+    /// generated. Generation runs on another thread. Dropping the stream
+    /// early aborts the query with GenieDialog_signal and waits for Genie to
+    /// return. Libraries without GenieDialog_signal can't be interrupted, so
+    /// then the drop blocks until the whole response has been generated.
+    pub fn query_stream(
+        &mut self,
+        prompt: &str,
+        sentence_code: SentenceCode,
+    ) -> Result<QueryStream, Error> {
+        let prompt = CString::new(prompt)?;
+        let (sender, receiver) = mpsc::channel();
+        let (handle, library) = {
+            let dialog = lock(&self.inner);
+            (dialog.handle, dialog.library.clone())
+        };
+
+        // The thread shares the dialog, so it stays alive even if the stream
+        // is leaked
+        let inner = self.inner.clone();
+        let worker = thread::spawn(move || {
+            let chunks = sender.clone();
+            let result = lock(&inner).query(&prompt, sentence_code, &mut |text, sentence_code| {
+                let chunk = QueryChunk {
+                    text: text.to_string(),
+                    sentence_code,
+                };
+                // The receiver is gone if the stream was dropped early
+                let _ = chunks.send(Ok(chunk));
+            });
+
+            if let Err(e) = result {
+                let _ = sender.send(Err(e));
+            }
+        });
+
+        Ok(QueryStream {
+            receiver,
+            worker: Some(worker),
+            handle,
+            library,
+            _dialog: self.inner.clone(),
+        })
+    }
+
+    /// Clears the conversation history, starting a new dialog with the same
+    /// models
+    pub fn reset(&mut self) -> Result<(), Error> {
+        let dialog = lock(&self.inner);
+        unsafe {
+            check(
+                dialog.library.bindings().GenieDialog_reset(dialog.handle),
+                "GenieDialog_reset",
+            )
+        }
+    }
+}
+
+impl DialogHandle {
+    fn query(
+        &self,
+        prompt: &CString,
+        sentence_code: SentenceCode,
+        callback: &mut dyn FnMut(&str, SentenceCode),
+    ) -> Result<(), Error> {
+        let mut state = QueryState {
+            callback,
+            panic: None,
+        };
+
+        let status = unsafe {
+            self.library.bindings().GenieDialog_query(
+                self.handle,
+                prompt.as_ptr(),
+                sentence_code.to_raw(),
+                Some(on_response),
+                &mut state as *mut QueryState as *const c_void,
+            )
+        };
+
+        // Panics can't unwind through Genie, so they're held until it returns
+        if let Some(payload) = state.panic {
+            panic::resume_unwind(payload);
+        }
+
+        check(status, "GenieDialog_query")
+    }
+}
+
+impl Drop for DialogHandle {
+    fn drop(&mut self) {
+        unsafe {
+            let status = self.library.bindings().GenieDialog_free(self.handle);
+            if let Err(e) = check(status, "GenieDialog_free") {
+                warn!(target: "GenieDialog", "Failed to free dialog: {}", e);
+            }
+        }
+    }
+}
+
+/// Chunks of a response, in the order they're generated. A failed query
+/// ends the stream with its error.
+pub struct QueryStream {
+    receiver: Receiver<Result<QueryChunk, Error>>,
+    worker: Option<JoinHandle<()>>,
+    /// Dialog running the query, signalled without taking the lock the
+    /// worker holds
+    handle: GenieDialog_Handle_t,
+    library: GenieLibrary,
+    /// Keeps the dialog alive for as long as it may be signalled
+    _dialog: Arc<Mutex<DialogHandle>>,
+}
+
+// The handle is only used to signal the dialog, which Genie allows from any
+// thread
+unsafe impl Send for QueryStream {}
+
+impl QueryStream {
+    /// Waits for the rest of the response and joins the chunks into one string
+    pub fn collect_text(self) -> Result<String, Error> {
+        let mut text = String::new();
+        for chunk in self {
+            text.push_str(&chunk?.text);
+        }
+
+        Ok(text)
+    }
+}
+
+impl Iterator for QueryStream {
+    type Item = Result<QueryChunk, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.receiver.recv().ok()
+    }
+}
+
+impl Drop for QueryStream {
+    fn drop(&mut self) {
+        if let Some(worker) = self.worker.take() {
+            // Nobody reads the rest of the response, so stop generating it
+            if let (false, Some(signal)) = (worker.is_finished(), self.library.signal()) {
+                let status = unsafe { signal(self.handle, ACTION_ABORT) };
+                if let Err(e) = check(status, "GenieDialog_signal") {
+                    warn!(target: "QueryStream", "Failed to abort query: {}", e);
+                }
+            }
+
+            if worker.join().is_err() {
+                warn!(target: "QueryStream", "Query callback panicked");
+            }
+        }
+    }
+}
+
+struct QueryState<'a> {
+    callback: &'a mut dyn FnMut(&str, SentenceCode),
+    panic: Option<Box<dyn Any + Send>>,
+}
+
+unsafe extern "C" fn on_response(
+    response: *const c_char,
+    sentence_code: GenieDialog_SentenceCode_t,
+    user_data: *const c_void,
+) {
+    let state = &mut *(user_data as *mut QueryState);
+    if state.panic.is_some() {
+        return;
+    }
+
+    let text = lossy_string(response);
+    let Some(sentence_code) = SentenceCode::from_raw(sentence_code) else {
+        warn!(target: "GenieDialog", "Ignoring response with sentence code {}", sentence_code);
+        return;
+    };
+
+    let callback = &mut state.callback;
+    if let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| callback(&text, sentence_code))) {
+        state.panic = Some(payload);
+    }
+}
+
+/// Locks a dialog, recovering it if a callback panicked during a query
+fn lock(dialog: &Mutex<DialogHandle>) -> std::sync::MutexGuard<'_, DialogHandle> {
+    dialog.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SentenceCode;
+
+    #[test]
+    fn sentence_code_round_trip() {
+        for code in [
+            SentenceCode::Complete,
+            SentenceCode::Begin,
+            SentenceCode::Continue,
+            SentenceCode::End,
+            SentenceCode::Abort,
+        ] {
+            assert_eq!(SentenceCode::from_raw(code.to_raw()), Some(code));
+        }
+    }
+}
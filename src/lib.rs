@@ -1,5 +1,34 @@
 #![allow(warnings)]
 
-mod dlcontainer;
-mod qairt;
-mod snpe;
+pub mod dlcontainer;
+mod errors;
+pub mod genie;
+#[cfg(feature = "psnpe")]
+pub mod psnpe;
+pub mod qairt;
+pub mod qnn;
+pub mod snpe;
+
+pub use dlcontainer::{DlContainer, DlContainerError, DlcRecord};
+pub use errors::{Error, ErrorDetails};
+pub use genie::{
+    GenieDialog, GenieDialogConfig, GenieLibrary, QueryChunk, QueryStream, SentenceCode,
+};
+#[cfg(feature = "psnpe")]
+pub use psnpe::{InferenceOutput, InferenceStream, PSnpe, PSnpeBuilder};
+pub use qairt::{Backend, Executor, Model, ModelFormat, ModelInput, ModelOutput};
+pub use snpe::{
+    get_available_devices, get_available_runtimes, get_version, packed_strides, AsSnpeTensor,
+    Device, ElementType, FromSnpeTensor, PerformanceProfile, Quantization, Runtime, RuntimeList,
+    Snpe, SnpeBuilder, SnpeLibrary, SnpeTensor, TensorData, TensorInfo, UserBufferElement,
+    UserBufferEncoding, UserBufferMap, VersionPolicy,
+};
+
+/// Commonly used types, meant to be glob imported
+pub mod prelude {
+    pub use crate::{
+        get_available_devices, get_version, AsSnpeTensor, Device, DlContainer, Error,
+        FromSnpeTensor, PerformanceProfile, Runtime, Snpe, SnpeBuilder, SnpeTensor, TensorInfo,
+        UserBufferEncoding, UserBufferMap,
+    };
+}
@@ -0,0 +1,838 @@
+mod builder;
+
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::future::Future;
+use std::marker::PhantomData;
+use std::mem::size_of;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+use std::task::{Context, Poll};
+
+use futures::Stream;
+use log::warn;
+use tensor_rs::tensor::Tensor;
+use tokio::sync::{mpsc, oneshot, OwnedSemaphorePermit, Semaphore};
+
+pub use self::builder::{PSnpeBuilder, RuntimeConfig, TransmissionMode};
+use crate::dlcontainer::DlContainer;
+use crate::errors::{lossy_string, Error};
+use crate::snpe::{
+    packed_strides, snpe_bindings, AsSnpeTensor, FromSnpeTensor, SnpeTensor, TensorData,
+    UserBufferEncoding, UserBufferMap,
+};
+
+/// Outputs of one inference as they come back from PSNPE
+type RawOutputs = HashMap<String, Vec<f32>>;
+
+/// Inference that completed, on its way to an InferenceStream
+struct Completed {
+    data_index: usize,
+    result: Result<RawOutputs, Error>,
+    /// Frees up room in the stream once the item is taken
+    _permit: OwnedSemaphorePermit,
+}
+
+/// Where the outputs of an inference are delivered
+enum Completion {
+    /// A single caller awaiting PSnpe::execute
+    Once(oneshot::Sender<Result<RawOutputs, Error>>),
+    /// An InferenceStream
+    Stream {
+        sender: mpsc::UnboundedSender<Completed>,
+        permit: OwnedSemaphorePermit,
+    },
+}
+
+impl Completion {
+    /// Hands the outputs of an inference to whoever is waiting on it. The
+    /// caller may have stopped waiting, which is fine.
+    fn complete(self, data_index: usize, result: Result<RawOutputs, Error>) {
+        match self {
+            Completion::Once(sender) => {
+                let _ = sender.send(result);
+            }
+            Completion::Stream { sender, permit } => {
+                let _ = sender.send(Completed {
+                    data_index,
+                    result,
+                    _permit: permit,
+                });
+            }
+        }
+    }
+}
+
+/// Buffers of an inference in output async mode, which PSNPE reads the
+/// inputs from and writes the outputs to until the output callback
+struct OutputAsyncBuffers {
+    // The maps are declared first, so they're dropped before the data they
+    // point into
+    inputs: UserBufferMap<'static>,
+    outputs: UserBufferMap<'static>,
+    _input_data: Vec<Vec<f32>>,
+    output_data: Vec<(String, Vec<f32>)>,
+}
+
+// The buffers are only touched by PSNPE until the output callback, then by
+// whichever thread takes them out of the pending inferences
+unsafe impl Send for OutputAsyncBuffers {}
+
+impl OutputAsyncBuffers {
+    /// Registers the staged inputs and room for every output
+    fn new(
+        staged: Vec<(CString, Vec<f32>)>,
+        input_shapes: &HashMap<String, Vec<usize>>,
+        output_shapes: &HashMap<String, Vec<usize>>,
+    ) -> Result<Self, Error> {
+        let mut buffers = Self {
+            inputs: UserBufferMap::new()?,
+            outputs: UserBufferMap::new()?,
+            _input_data: vec![],
+            output_data: vec![],
+        };
+
+        for (name, mut data) in staged {
+            let name = name.to_string_lossy().to_string();
+            let shape = match input_shapes.get(&name) {
+                Some(shape) if shape.iter().product::<usize>() == data.len() => shape.clone(),
+                Some(shape) => {
+                    return Err(Error::ShapeMismatch(format!(
+                        "input {} has {} values but the network expects {:?}",
+                        name,
+                        data.len(),
+                        shape
+                    )))
+                }
+                None => vec![data.len()],
+            };
+
+            buffers.inputs.insert(
+                &name,
+                unsafe { owned_slice(&mut data) },
+                &packed_strides(&shape, size_of::<f32>()),
+                UserBufferEncoding::Float,
+            )?;
+            buffers._input_data.push(data);
+        }
+
+        for (name, shape) in output_shapes {
+            let mut data = vec![0.0; shape.iter().product()];
+            buffers.outputs.insert(
+                name,
+                unsafe { owned_slice(&mut data) },
+                &packed_strides(shape, size_of::<f32>()),
+                UserBufferEncoding::Float,
+            )?;
+            buffers.output_data.push((name.clone(), data));
+        }
+
+        Ok(buffers)
+    }
+
+    /// Releases the buffers and returns the outputs PSNPE wrote
+    fn into_outputs(self) -> RawOutputs {
+        let Self {
+            inputs,
+            outputs,
+            output_data,
+            ..
+        } = self;
+        drop(inputs);
+        drop(outputs);
+
+        output_data.into_iter().collect()
+    }
+}
+
+/// Lends out the heap memory of a vector for as long as its owner keeps it.
+/// Moving the vector doesn't move its elements, so the slice stays valid
+/// until the vector is dropped, which the caller must make sure happens last.
+unsafe fn owned_slice(data: &mut Vec<f32>) -> &'static mut [f32] {
+    std::slice::from_raw_parts_mut(data.as_mut_ptr(), data.len())
+}
+
+/// An inference submitted to PSNPE that hasn't completed yet
+struct Pending {
+    /// Inputs waiting for the input callback to pick them up
+    inputs: Option<Vec<(CString, Vec<f32>)>>,
+    /// Buffers PSNPE runs the inference on in output async mode
+    buffers: Option<OutputAsyncBuffers>,
+    /// Id of the PSnpe instance running the inference
+    owner: usize,
+    completion: Completion,
+}
+
+/// Data indices handed out to inferences. They're unique across every
+/// instance since the callbacks are shared.
+static NEXT_INDEX: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the inferences in flight, keyed by data index. The PSNPE callbacks
+/// don't carry any user data, so the index is the only way back to a caller.
+fn pending() -> MutexGuard<'static, HashMap<usize, Pending>> {
+    static PENDING: OnceLock<Mutex<HashMap<usize, Pending>>> = OnceLock::new();
+
+    PENDING
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+}
+
+/// Hands the staged inputs of an inference over to PSNPE. The first entry of
+/// the key list is the data index given to Snpe_PSNPE_ExecuteInputOutputAsync.
+unsafe extern "C" fn input_callback(
+    keys: snpe_bindings::Snpe_StringList_Handle_t,
+    _names: snpe_bindings::Snpe_StringList_Handle_t,
+) -> snpe_bindings::Snpe_ApplicationBufferMap_Handle_t {
+    let snpe = snpe_bindings::loaded();
+    let buffers = snpe.Snpe_ApplicationBufferMap_Create();
+
+    let key = lossy_string(snpe.Snpe_StringList_At(keys, 0));
+    let inputs = key
+        .parse::<usize>()
+        .ok()
+        .and_then(|index| pending().get_mut(&index)?.inputs.take());
+
+    match inputs {
+        // The buffer map copies the data, so the staged inputs can be dropped
+        Some(inputs) => {
+            for (name, data) in &inputs {
+                snpe.Snpe_ApplicationBufferMap_AddFloat(
+                    buffers,
+                    name.as_ptr(),
+                    data.as_ptr(),
+                    data.len(),
+                );
+            }
+        }
+        None => warn!(target: "PSnpe", "No inputs staged for inference {}", key),
+    }
+
+    buffers
+}
+
+/// Delivers the outputs of a completed inference to whoever is waiting on it
+unsafe extern "C" fn output_callback(
+    param: snpe_bindings::Snpe_PSNPE_InputOutputAsyncCallbackParam_Handle_t,
+) {
+    let snpe = snpe_bindings::loaded();
+    let index = snpe.Snpe_PSNPE_InputOutputAsyncCallbackParam_GetDataIdx(param);
+
+    let Some(inference) = pending().remove(&index) else {
+        warn!(target: "PSnpe", "Received outputs for unknown inference {}", index);
+        return;
+    };
+
+    let result = if snpe.Snpe_PSNPE_InputOutputAsyncCallbackParam_GetExecuteStatus(param) != 0 {
+        Ok(read_outputs(snpe, param))
+    } else {
+        let message = snpe.Snpe_PSNPE_InputOutputAsyncCallbackParam_GetErrorMsg(param);
+        Err(Error::ExecuteFailed(format!(
+            "data index {}: {}",
+            index,
+            lossy_string(message)
+        )))
+    };
+
+    inference.completion.complete(index, result);
+}
+
+/// Delivers the outputs PSNPE wrote to the buffers of an inference in output
+/// async mode, once the callback reports it completed
+unsafe extern "C" fn output_async_callback(
+    param: snpe_bindings::Snpe_PSNPE_OutputAsyncCallbackParam_Handle_t,
+) {
+    let snpe = snpe_bindings::loaded();
+    let index = snpe.Snpe_PSNPE_OutputAsyncCallbackParam_GetDataIdx(param);
+
+    let Some(inference) = pending().remove(&index) else {
+        warn!(target: "PSnpe", "Received outputs for unknown inference {}", index);
+        return;
+    };
+
+    let result = if snpe.Snpe_PSNPE_OutputAsyncCallbackParam_GetExecuteStatus(param) != 0 {
+        inference
+            .buffers
+            .map(OutputAsyncBuffers::into_outputs)
+            .ok_or_else(|| Error::InvalidState(format!("No buffers for inference {}", index)))
+    } else {
+        let message = snpe.Snpe_PSNPE_OutputAsyncCallbackParam_GetErrorMsg(param);
+        Err(Error::ExecuteFailed(format!(
+            "data index {}: {}",
+            index,
+            lossy_string(message)
+        )))
+    };
+
+    inference.completion.complete(index, result);
+}
+
+/// Copies the float outputs out of the callback parameter
+unsafe fn read_outputs(
+    snpe: &snpe_bindings::SNPE,
+    param: snpe_bindings::Snpe_PSNPE_InputOutputAsyncCallbackParam_Handle_t,
+) -> RawOutputs {
+    let mut outputs = HashMap::new();
+
+    let names = snpe.Snpe_PSNPE_InputOutputAsyncCallbackParam_GetUserBufferNames(param);
+    if names.is_null() {
+        return outputs;
+    }
+
+    for i in 0..snpe.Snpe_StringList_Size(names) {
+        let cname = CStr::from_ptr(snpe.Snpe_StringList_At(names, i));
+        let buffer =
+            snpe.Snpe_PSNPE_InputOutputAsyncCallbackParam_GetUserBuffer(param, cname.as_ptr());
+
+        let values = if buffer.data.is_null() {
+            vec![]
+        } else {
+            let len = buffer.size / size_of::<f32>();
+            std::slice::from_raw_parts(buffer.data as *const f32, len).to_vec()
+        };
+
+        outputs.insert(cname.to_string_lossy().to_string(), values);
+    }
+
+    snpe.Snpe_StringList_Delete(names);
+    outputs
+}
+
+/// Pool of network instances running inferences in parallel
+pub struct PSnpe {
+    handle: snpe_bindings::Snpe_PSNPE_Handle_t,
+    id: usize,
+    transmission_mode: TransmissionMode,
+    input_names: Vec<CString>,
+    input_shapes: HashMap<String, Vec<usize>>,
+    output_shapes: Arc<HashMap<String, Vec<usize>>>,
+}
+
+impl PSnpe {
+    /// Returns a builder to create a new pool from the container
+    pub fn builder(container: &DlContainer) -> PSnpeBuilder<'_> {
+        PSnpeBuilder::new(container)
+    }
+
+    /// Returns the names of the input tensors
+    pub fn get_input_tensor_names(&self) -> Vec<String> {
+        self.input_names
+            .iter()
+            .map(|name| name.to_string_lossy().to_string())
+            .collect()
+    }
+
+    /// Returns the names of the output tensors
+    pub fn get_output_tensor_names(&self) -> Vec<String> {
+        self.output_shapes.keys().cloned().collect()
+    }
+
+    /// Submits the inputs to the pool and returns a future resolving to the
+    /// outputs. Inferences are submitted right away, so several can be in
+    /// flight before any is awaited. The pool must be built in one of the
+    /// async modes.
+    pub fn execute<I, O>(
+        &self,
+        inputs: &HashMap<String, I>,
+    ) -> impl Future<Output = Result<HashMap<String, O>, Error>> + Send + 'static
+    where
+        I: AsSnpeTensor,
+        O: FromSnpeTensor + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        let submitted = stage(inputs)
+            .and_then(|staged| self.submit(staged, Completion::Once(sender)))
+            .map(|_| receiver);
+        let shapes = self.output_shapes.clone();
+
+        async move {
+            let outputs = submitted?.await.map_err(|_| {
+                Error::InvalidState("PSNPE was dropped before the inference completed".to_string())
+            })??;
+
+            to_tensors(outputs, &shapes)
+        }
+    }
+
+    /// Creates a stream that yields the outputs of inferences as they
+    /// complete, along with the submitter that starts them. At most capacity
+    /// inferences can be in flight or waiting in the stream, so a slow
+    /// consumer holds back submissions. The stream ends once the submitter is
+    /// dropped and every inference has been yielded. Like execute, it needs
+    /// the pool built in one of the async modes. Each item carries the data
+    /// index PSNPE reported the inference under.
+    pub fn stream<O>(&self, capacity: usize) -> (InferenceSubmitter<'_>, InferenceStream<O>)
+    where
+        O: FromSnpeTensor,
+    {
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        let submitter = InferenceSubmitter {
+            psnpe: self,
+            sender,
+            permits: Arc::new(Semaphore::new(capacity.max(1))),
+        };
+
+        let stream = InferenceStream {
+            receiver,
+            shapes: self.output_shapes.clone(),
+            _output: PhantomData,
+        };
+
+        (submitter, stream)
+    }
+
+    /// Runs a batch of inferences on the registered buffers, one input and
+    /// one output map per inference, and returns once they've all completed.
+    /// The pool must be built in sync mode.
+    pub fn execute_user_buffers(
+        &self,
+        inputs: &[UserBufferMap],
+        outputs: &mut [UserBufferMap],
+    ) -> Result<(), Error> {
+        self.require_mode(TransmissionMode::Sync)?;
+
+        if inputs.len() != outputs.len() {
+            return Err(Error::InvalidArgument(format!(
+                "{} input maps but {} output maps",
+                inputs.len(),
+                outputs.len()
+            )));
+        }
+
+        unsafe {
+            let snpe = snpe_bindings::loaded();
+            let input_list = user_buffer_list(snpe, inputs)?;
+            let output_list = match user_buffer_list(snpe, outputs) {
+                Ok(list) => list,
+                Err(error) => {
+                    snpe.Snpe_UserBufferList_Delete(input_list);
+                    return Err(error);
+                }
+            };
+
+            let code = snpe.Snpe_PSNPE_Execute(self.handle, input_list, output_list);
+            snpe.Snpe_UserBufferList_Delete(input_list);
+            snpe.Snpe_UserBufferList_Delete(output_list);
+
+            if code != snpe_bindings::Snpe_ErrorCode_t_SNPE_SUCCESS {
+                let message = lossy_string(snpe.Snpe_PSNPE_GetLastErrorString(self.handle));
+                return Err(Error::from_code(code, message, String::new()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fails unless the pool was built in the given mode
+    fn require_mode(&self, mode: TransmissionMode) -> Result<(), Error> {
+        if self.transmission_mode != mode {
+            return Err(Error::InvalidState(format!(
+                "PSNPE must be built in {:?} mode for this, not {:?}",
+                mode, self.transmission_mode
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Starts an inference on the staged inputs, returning its data index
+    fn submit(
+        &self,
+        staged: Vec<(CString, Vec<f32>)>,
+        completion: Completion,
+    ) -> Result<usize, Error> {
+        let index = NEXT_INDEX.fetch_add(1, Ordering::Relaxed);
+
+        match self.transmission_mode {
+            TransmissionMode::InputOutputAsync => {
+                self.submit_input_output(index, staged, completion)?
+            }
+            TransmissionMode::OutputAsync => self.submit_output(index, staged, completion)?,
+            TransmissionMode::Sync => {
+                return Err(Error::InvalidState(
+                    "PSNPE must be built in an async mode for this, not Sync".to_string(),
+                ))
+            }
+        }
+
+        Ok(index)
+    }
+
+    /// Starts an inference in output async mode, running it on buffers that
+    /// are kept with the pending inference until the output callback
+    fn submit_output(
+        &self,
+        index: usize,
+        staged: Vec<(CString, Vec<f32>)>,
+        completion: Completion,
+    ) -> Result<(), Error> {
+        let buffers = OutputAsyncBuffers::new(staged, &self.input_shapes, &self.output_shapes)?;
+        let (inputs, outputs) = (buffers.inputs.handle(), buffers.outputs.handle());
+
+        // Registered before executing, since the callback may fire right away
+        pending().insert(
+            index,
+            Pending {
+                inputs: None,
+                buffers: Some(buffers),
+                owner: self.id,
+                completion,
+            },
+        );
+
+        unsafe {
+            let snpe = snpe_bindings::loaded();
+            let code = snpe.Snpe_PSNPE_ExecuteOutputAsync(self.handle, inputs, outputs, index);
+
+            if code != snpe_bindings::Snpe_ErrorCode_t_SNPE_SUCCESS {
+                pending().remove(&index);
+
+                let message = lossy_string(snpe.Snpe_PSNPE_GetLastErrorString(self.handle));
+                return Err(Error::from_code(code, message, String::new()));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Starts an inference in input/output async mode, leaving the inputs
+    /// for the input callback to pick up
+    fn submit_input_output(
+        &self,
+        index: usize,
+        staged: Vec<(CString, Vec<f32>)>,
+        completion: Completion,
+    ) -> Result<(), Error> {
+        let key = CString::new(index.to_string())?;
+
+        pending().insert(
+            index,
+            Pending {
+                inputs: Some(staged),
+                buffers: None,
+                owner: self.id,
+                completion,
+            },
+        );
+
+        unsafe {
+            let snpe = snpe_bindings::loaded();
+            let keys = snpe.Snpe_StringList_Create();
+            snpe.Snpe_StringList_Append(keys, key.as_ptr());
+
+            let code = snpe.Snpe_PSNPE_ExecuteInputOutputAsync(self.handle, keys, index, 0, 0);
+            snpe.Snpe_StringList_Delete(keys);
+
+            if code != snpe_bindings::Snpe_ErrorCode_t_SNPE_SUCCESS {
+                pending().remove(&index);
+
+                let message = lossy_string(snpe.Snpe_PSNPE_GetLastErrorString(self.handle));
+                return Err(Error::from_code(code, message, String::new()));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Outputs of one inference delivered by an InferenceStream
+pub struct InferenceOutput<O = Tensor> {
+    /// Data index returned when the inference was submitted
+    pub data_index: usize,
+    /// Output tensors by name
+    pub outputs: HashMap<String, O>,
+}
+
+/// Starts inferences whose outputs are delivered to an InferenceStream
+pub struct InferenceSubmitter<'a> {
+    psnpe: &'a PSnpe,
+    sender: mpsc::UnboundedSender<Completed>,
+    permits: Arc<Semaphore>,
+}
+
+impl<'a> InferenceSubmitter<'a> {
+    /// Waits for room in the stream, then starts an inference on the inputs
+    /// and returns its data index
+    pub fn submit<I>(
+        &self,
+        inputs: &HashMap<String, I>,
+    ) -> impl Future<Output = Result<usize, Error>> + '_
+    where
+        I: AsSnpeTensor,
+    {
+        let staged = stage(inputs);
+
+        async move {
+            let staged = staged?;
+            let permit = self
+                .permits
+                .clone()
+                .acquire_owned()
+                .await
+                .map_err(|_| Error::InvalidState("Inference stream was closed".to_string()))?;
+
+            let completion = Completion::Stream {
+                sender: self.sender.clone(),
+                permit,
+            };
+            self.psnpe.submit(staged, completion)
+        }
+    }
+}
+
+/// Stream of inference outputs, in the order the inferences complete
+pub struct InferenceStream<O = Tensor> {
+    receiver: mpsc::UnboundedReceiver<Completed>,
+    shapes: Arc<HashMap<String, Vec<usize>>>,
+    _output: PhantomData<fn() -> O>,
+}
+
+impl<O: FromSnpeTensor> Stream for InferenceStream<O> {
+    type Item = Result<InferenceOutput<O>, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx).map(|completed| {
+            completed.map(|completed| {
+                Ok(InferenceOutput {
+                    data_index: completed.data_index,
+                    outputs: to_tensors(completed.result?, &self.shapes)?,
+                })
+            })
+        })
+    }
+}
+
+/// Collects the maps of a batch into a c list, which the caller must delete.
+/// The list only refers to the maps, which stay owned by the caller.
+unsafe fn user_buffer_list(
+    snpe: &snpe_bindings::SNPE,
+    maps: &[UserBufferMap],
+) -> Result<snpe_bindings::Snpe_UserBufferList_Handle_t, Error> {
+    let list = snpe.Snpe_UserBufferList_Create();
+    if list.is_null() {
+        return Err(Error::last(snpe));
+    }
+
+    for map in maps {
+        snpe.Snpe_UserBufferList_PushBack(list, map.handle());
+    }
+
+    Ok(list)
+}
+
+/// Copies the inputs as floats so they can be handed to the input callback later
+fn stage<I>(inputs: &HashMap<String, I>) -> Result<Vec<(CString, Vec<f32>)>, Error>
+where
+    I: AsSnpeTensor,
+{
+    inputs
+        .iter()
+        .map(|(name, tensor)| {
+            Ok((
+                CString::new(name.as_str())?,
+                tensor.to_snpe_tensor()?.to_f32(),
+            ))
+        })
+        .collect()
+}
+
+/// Wraps raw outputs in tensors of the reported shapes
+fn to_tensors<O>(
+    outputs: RawOutputs,
+    shapes: &HashMap<String, Vec<usize>>,
+) -> Result<HashMap<String, O>, Error>
+where
+    O: FromSnpeTensor,
+{
+    outputs
+        .into_iter()
+        .map(|(name, values)| {
+            let tensor = to_tensor(values, shapes.get(&name))?;
+            Ok((name, O::from_snpe_tensor(tensor)?))
+        })
+        .collect()
+}
+
+/// Wraps output values in a tensor of the reported shape, falling back to a
+/// flat tensor if the shape doesn't fit the values
+fn to_tensor(values: Vec<f32>, shape: Option<&Vec<usize>>) -> Result<SnpeTensor, Error> {
+    let shape = match shape {
+        Some(shape) if shape.iter().product::<usize>() == values.len() => shape.clone(),
+        _ => vec![values.len()],
+    };
+
+    SnpeTensor::new(&shape, TensorData::Float(values))
+}
+
+impl Drop for PSnpe {
+    fn drop(&mut self) {
+        unsafe {
+            let snpe = snpe_bindings::loaded();
+            let errorCode = snpe.Snpe_PSNPE_Delete(self.handle);
+
+            if errorCode != snpe_bindings::Snpe_ErrorCode_t_SNPE_SUCCESS {
+                let error = Error::last(snpe);
+                warn!(target: "PSnpe", "Error cleaning up PSNPE: {}", error);
+            }
+        }
+
+        // Anything still waiting will never complete, so fail it
+        pending().retain(|_, inference| inference.owner != self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use futures::StreamExt;
+    use tensor_rs::tensor::Tensor;
+
+    use super::{PSnpe, RuntimeConfig, TransmissionMode};
+    use crate::dlcontainer::DlContainer;
+    use crate::errors::Error;
+    use crate::snpe::{packed_strides, Runtime, UserBufferEncoding, UserBufferMap};
+
+    #[tokio::test]
+    async fn test_execute_resnet50() {
+        let container = DlContainer::from_path("test/data/resnet50.dlc").unwrap();
+        let network = PSnpe::builder(&container)
+            .instances(2, RuntimeConfig::new(Runtime::CPU))
+            .build()
+            .unwrap();
+
+        let name = network.get_input_tensor_names().remove(0);
+        let image = Tensor::from_vec_f32(&vec![0.0; 224 * 224 * 3], &[1, 224, 224, 3]);
+        let inputs = HashMap::from([(name, image)]);
+
+        // Both inferences are in flight before either is awaited
+        let first = network.execute(&inputs);
+        let second = network.execute(&inputs);
+
+        let outputs: [HashMap<String, Tensor>; 2] = [first.await.unwrap(), second.await.unwrap()];
+        for outputs in outputs {
+            assert_eq!(outputs.len(), 1);
+
+            let probabilities = outputs.values().next().unwrap();
+            assert_eq!(probabilities.numel(), 1000);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_requires_async_mode() {
+        let container = DlContainer::from_path("test/data/resnet50.dlc").unwrap();
+        let network = PSnpe::builder(&container)
+            .transmission_mode(TransmissionMode::Sync)
+            .build()
+            .unwrap();
+
+        let result = network.execute::<Tensor, Tensor>(&HashMap::new()).await;
+        assert!(matches!(result, Err(Error::InvalidState(_))));
+    }
+
+    #[test]
+    fn test_execute_user_buffers_resnet50() {
+        let container = DlContainer::from_path("test/data/resnet50.dlc").unwrap();
+        let network = PSnpe::builder(&container)
+            .instances(2, RuntimeConfig::new(Runtime::CPU))
+            .transmission_mode(TransmissionMode::Sync)
+            .build()
+            .unwrap();
+
+        let input_name = network.get_input_tensor_names().remove(0);
+        let output_name = network.get_output_tensor_names().remove(0);
+
+        let mut images = vec![vec![0.0f32; 224 * 224 * 3]; 2];
+        let mut probabilities = vec![vec![0.0f32; 1000]; 2];
+
+        let mut inputs = vec![];
+        for image in &mut images {
+            let mut map = UserBufferMap::new().unwrap();
+            let strides = packed_strides(&[1, 224, 224, 3], 4);
+            map.insert(&input_name, image, &strides, UserBufferEncoding::Float)
+                .unwrap();
+            inputs.push(map);
+        }
+
+        let mut outputs = vec![];
+        for output in &mut probabilities {
+            let mut map = UserBufferMap::new().unwrap();
+            let strides = packed_strides(&[1, 1000], 4);
+            map.insert(&output_name, output, &strides, UserBufferEncoding::Float)
+                .unwrap();
+            outputs.push(map);
+        }
+
+        network.execute_user_buffers(&inputs, &mut outputs).unwrap();
+
+        for output in &outputs {
+            let total: f32 = output.get::<f32>(&output_name).unwrap().iter().sum();
+            assert!((total - 1.0).abs() < 1e-3);
+        }
+
+        let result = network.execute_user_buffers(&inputs, &mut outputs[..1]);
+        assert!(matches!(result, Err(Error::InvalidArgument(_))));
+    }
+
+    #[tokio::test]
+    async fn test_stream_resnet50() {
+        let container = DlContainer::from_path("test/data/resnet50.dlc").unwrap();
+        let network = PSnpe::builder(&container)
+            .instances(2, RuntimeConfig::new(Runtime::CPU))
+            .build()
+            .unwrap();
+
+        let name = network.get_input_tensor_names().remove(0);
+        let image = Tensor::from_vec_f32(&vec![0.0; 224 * 224 * 3], &[1, 224, 224, 3]);
+        let inputs = HashMap::from([(name, image)]);
+
+        let (submitter, stream) = network.stream::<Tensor>(4);
+        let mut submitted = HashSet::new();
+        for _ in 0..3 {
+            submitted.insert(submitter.submit(&inputs).await.unwrap());
+        }
+        drop(submitter);
+
+        let outputs: Vec<_> = stream.collect().await;
+        assert_eq!(outputs.len(), 3);
+
+        let completed: HashSet<usize> = outputs
+            .into_iter()
+            .map(|output| output.unwrap().data_index)
+            .collect();
+        assert_eq!(completed, submitted);
+    }
+
+    #[tokio::test]
+    async fn test_stream_output_async_resnet50() {
+        let container = DlContainer::from_path("test/data/resnet50.dlc").unwrap();
+        let network = PSnpe::builder(&container)
+            .instances(2, RuntimeConfig::new(Runtime::CPU))
+            .transmission_mode(TransmissionMode::OutputAsync)
+            .build()
+            .unwrap();
+
+        let name = network.get_input_tensor_names().remove(0);
+        let image = Tensor::from_vec_f32(&vec![0.0; 224 * 224 * 3], &[1, 224, 224, 3]);
+        let inputs = HashMap::from([(name, image)]);
+
+        let (submitter, stream) = network.stream::<Tensor>(2);
+        let mut submitted = HashSet::new();
+        for _ in 0..3 {
+            submitted.insert(submitter.submit(&inputs).await.unwrap());
+        }
+        drop(submitter);
+
+        let outputs: Vec<_> = stream.collect().await;
+        let mut completed = HashSet::new();
+        for output in outputs {
+            let output = output.unwrap();
+            let probabilities = output.outputs.values().next().unwrap();
+            assert_eq!(probabilities.numel(), 1000);
+            completed.insert(output.data_index);
+        }
+        assert_eq!(completed, submitted);
+    }
+}
@@ -0,0 +1,398 @@
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use super::{input_callback, output_async_callback, output_callback, PSnpe};
+use crate::dlcontainer::DlContainer;
+use crate::errors::Error;
+use crate::snpe::{
+    check, read_shape, snpe_bindings, string_list, PerformanceProfile, Runtime, RuntimeList,
+};
+
+/// Ids handed out to PSNPE instances, so pending inferences can be traced
+/// back to the instance running them
+static NEXT_INSTANCE: AtomicUsize = AtomicUsize::new(0);
+
+/// How inputs and outputs are passed to PSNPE
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TransmissionMode {
+    /// Batches of inputs run to completion before returning
+    Sync,
+    /// Inputs are passed in directly, outputs come back through a callback.
+    /// Used by PSnpe::execute and PSnpe::stream.
+    OutputAsync,
+    /// Inputs are pulled and outputs pushed through callbacks. Used by
+    /// PSnpe::execute and PSnpe::stream.
+    #[default]
+    InputOutputAsync,
+}
+
+impl TransmissionMode {
+    /// Returns the SNPE transmission mode id
+    fn id(&self) -> snpe_bindings::Snpe_PSNPE_InputOutputTransmissionMode_t {
+        match self {
+            TransmissionMode::Sync => {
+                snpe_bindings::Snpe_PSNPE_InputOutputTransmissionMode_t_SNPE_PSNPE_INPUTOUTPUTTRANSMISSIONMODE_SYNC
+            }
+            TransmissionMode::OutputAsync => {
+                snpe_bindings::Snpe_PSNPE_InputOutputTransmissionMode_t_SNPE_PSNPE_INPUTOUTPUTTRANSMISSIONMODE_OUTPUTASYNC
+            }
+            TransmissionMode::InputOutputAsync => {
+                snpe_bindings::Snpe_PSNPE_InputOutputTransmissionMode_t_SNPE_PSNPE_INPUTOUTPUTTRANSMISSIONMODE_INPUTOUTPUTASYNC
+            }
+        }
+    }
+}
+
+/// Configuration of a single network instance in the PSNPE pool
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeConfig {
+    runtime: Runtime,
+    fallback: Vec<Runtime>,
+    performance_profile: PerformanceProfile,
+    cpu_fallback: bool,
+}
+
+impl RuntimeConfig {
+    /// Creates a config running on the given runtime with the default profile
+    pub fn new(runtime: Runtime) -> Self {
+        Self {
+            runtime,
+            fallback: vec![],
+            performance_profile: PerformanceProfile::default(),
+            cpu_fallback: false,
+        }
+    }
+
+    /// Sets the runtimes to try, in order, for layers the runtime doesn't support
+    pub fn fallback(mut self, runtimes: &[Runtime]) -> Self {
+        self.fallback = runtimes.to_vec();
+        self
+    }
+
+    /// Sets the performance profile of the instance
+    pub fn performance_profile(mut self, profile: PerformanceProfile) -> Self {
+        self.performance_profile = profile;
+        self
+    }
+
+    /// Sets whether layers can fall back to the cpu
+    pub fn cpu_fallback(mut self, enabled: bool) -> Self {
+        self.cpu_fallback = enabled;
+        self
+    }
+
+    /// Creates the c runtime config, which the caller must delete
+    unsafe fn create(
+        &self,
+        snpe: &snpe_bindings::SNPE,
+    ) -> Result<snpe_bindings::Snpe_RuntimeConfig_Handle_t, Error> {
+        let handle = snpe.Snpe_RuntimeConfig_Create();
+        if handle.is_null() {
+            return Err(Error::last(snpe));
+        }
+
+        let result = self.configure(snpe, handle);
+        if let Err(error) = result {
+            snpe.Snpe_RuntimeConfig_Delete(handle);
+            return Err(error);
+        }
+
+        Ok(handle)
+    }
+
+    /// Applies the options to the c runtime config
+    unsafe fn configure(
+        &self,
+        snpe: &snpe_bindings::SNPE,
+        handle: snpe_bindings::Snpe_RuntimeConfig_Handle_t,
+    ) -> Result<(), Error> {
+        check(
+            snpe,
+            snpe.Snpe_RuntimeConfig_SetRuntime(handle, self.runtime.id()),
+        )?;
+
+        check(
+            snpe,
+            snpe.Snpe_RuntimeConfig_SetPerformanceProfile(handle, self.performance_profile.id()),
+        )?;
+
+        check(
+            snpe,
+            snpe.Snpe_RuntimeConfig_SetEnableCPUFallback(handle, self.cpu_fallback as i32),
+        )?;
+
+        if !self.fallback.is_empty() {
+            let runtime_list = RuntimeList::from_runtimes(&self.fallback)?;
+            check(
+                snpe,
+                snpe.Snpe_RuntimeConfig_SetRuntimeList(handle, runtime_list.handle()),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Builds a pool of networks from a loaded DlContainer
+pub struct PSnpeBuilder<'a> {
+    container: &'a DlContainer,
+    runtime_configs: Vec<RuntimeConfig>,
+    output_tensors: Vec<String>,
+    transmission_mode: TransmissionMode,
+    input_threads: usize,
+    output_threads: usize,
+}
+
+impl<'a> PSnpeBuilder<'a> {
+    /// Creates a new builder for the given container. Without any further
+    /// configuration the pool holds a single cpu instance.
+    pub fn new(container: &'a DlContainer) -> Self {
+        Self {
+            container,
+            runtime_configs: vec![],
+            output_tensors: vec![],
+            transmission_mode: TransmissionMode::default(),
+            input_threads: 1,
+            output_threads: 1,
+        }
+    }
+
+    /// Adds a network instance to the pool
+    pub fn runtime_config(mut self, config: RuntimeConfig) -> Self {
+        self.runtime_configs.push(config);
+        self
+    }
+
+    /// Adds a number of identical network instances to the pool
+    pub fn instances(mut self, count: usize, config: RuntimeConfig) -> Self {
+        self.runtime_configs
+            .extend(std::iter::repeat(config).take(count));
+        self
+    }
+
+    /// Sets the names of the tensors that should be returned
+    pub fn output_tensors<S>(mut self, names: &[S]) -> Self
+    where
+        S: AsRef<str>,
+    {
+        self.output_tensors = names.iter().map(|s| s.as_ref().to_string()).collect();
+        self
+    }
+
+    /// Sets how inputs and outputs are passed to the pool
+    pub fn transmission_mode(mut self, mode: TransmissionMode) -> Self {
+        self.transmission_mode = mode;
+        self
+    }
+
+    /// Sets the number of threads preparing inputs in async modes
+    pub fn input_threads(mut self, count: usize) -> Self {
+        self.input_threads = count;
+        self
+    }
+
+    /// Sets the number of threads delivering outputs in async modes
+    pub fn output_threads(mut self, count: usize) -> Self {
+        self.output_threads = count;
+        self
+    }
+
+    /// Builds the pool
+    pub fn build(self) -> Result<PSnpe, Error> {
+        let snpe = unsafe { snpe_bindings::get()? };
+        let handle = unsafe { snpe.Snpe_PSNPE_Create() };
+
+        if handle.is_null() {
+            return Err(unsafe { Error::last(snpe) });
+        }
+
+        let result = unsafe { self.build_handle(snpe, handle) }.and_then(|_| unsafe {
+            let input_names = read_names(snpe, snpe.Snpe_PSNPE_GetInputTensorNames(handle));
+            let output_names = read_names(snpe, snpe.Snpe_PSNPE_GetOutputTensorNames(handle));
+
+            let input_shapes = buffer_shapes(snpe, handle, &input_names);
+            let output_shapes = buffer_shapes(snpe, handle, &output_names);
+
+            Ok(PSnpe {
+                handle,
+                id: NEXT_INSTANCE.fetch_add(1, Ordering::Relaxed),
+                transmission_mode: self.transmission_mode,
+                input_names,
+                input_shapes,
+                output_shapes: Arc::new(output_shapes),
+            })
+        });
+
+        if result.is_err() {
+            unsafe {
+                snpe.Snpe_PSNPE_Delete(handle);
+            }
+        }
+
+        result
+    }
+
+    /// Creates the c build config and builds the pool with it
+    unsafe fn build_handle(
+        &self,
+        snpe: &snpe_bindings::SNPE,
+        handle: snpe_bindings::Snpe_PSNPE_Handle_t,
+    ) -> Result<(), Error> {
+        let output_names = if self.output_tensors.is_empty() {
+            None
+        } else {
+            Some(string_list(snpe, &self.output_tensors)?)
+        };
+
+        let build_config = snpe.Snpe_BuildConfig_Create();
+        let runtime_configs = snpe.Snpe_RuntimeConfigList_Create();
+
+        // Every c object is freed once the pool is built, whether or not that succeeds
+        let result = if build_config.is_null() || runtime_configs.is_null() {
+            Err(Error::last(snpe))
+        } else {
+            self.configure(snpe, build_config, runtime_configs, output_names)
+                .and_then(|_| check(snpe, snpe.Snpe_PSNPE_Build(handle, build_config)))
+        };
+
+        snpe.Snpe_BuildConfig_Delete(build_config);
+        snpe.Snpe_RuntimeConfigList_Delete(runtime_configs);
+        if let Some(names) = output_names {
+            snpe.Snpe_StringList_Delete(names);
+        }
+
+        result
+    }
+
+    /// Applies the builder options to the c build config
+    unsafe fn configure(
+        &self,
+        snpe: &snpe_bindings::SNPE,
+        build_config: snpe_bindings::Snpe_BuildConfig_Handle_t,
+        runtime_configs: snpe_bindings::Snpe_RuntimeConfigList_Handle_t,
+        output_names: Option<snpe_bindings::Snpe_StringList_Handle_t>,
+    ) -> Result<(), Error> {
+        let defaults = [RuntimeConfig::new(Runtime::CPU)];
+        let configs = if self.runtime_configs.is_empty() {
+            &defaults[..]
+        } else {
+            &self.runtime_configs[..]
+        };
+
+        for config in configs {
+            let runtime_config = config.create(snpe)?;
+            let code = snpe.Snpe_RuntimeConfigList_PushBack(runtime_configs, runtime_config);
+            snpe.Snpe_RuntimeConfig_Delete(runtime_config);
+            check(snpe, code)?;
+        }
+
+        check(
+            snpe,
+            snpe.Snpe_BuildConfig_SetContainer(build_config, self.container.handle),
+        )?;
+        check(
+            snpe,
+            snpe.Snpe_BuildConfig_SetRuntimeConfigList(build_config, runtime_configs),
+        )?;
+        check(
+            snpe,
+            snpe.Snpe_BuildConfig_SetInputOutputTransmissionMode(
+                build_config,
+                self.transmission_mode.id(),
+            ),
+        )?;
+        check(
+            snpe,
+            snpe.Snpe_BuildConfig_SetInputThreadNumbers(build_config, self.input_threads),
+        )?;
+        check(
+            snpe,
+            snpe.Snpe_BuildConfig_SetOutputThreadNumbers(build_config, self.output_threads),
+        )?;
+
+        if self.transmission_mode == TransmissionMode::OutputAsync {
+            check(
+                snpe,
+                snpe.Snpe_BuildConfig_SetOutputCallback(build_config, Some(output_async_callback)),
+            )?;
+        }
+
+        if self.transmission_mode == TransmissionMode::InputOutputAsync {
+            check(
+                snpe,
+                snpe.Snpe_BuildConfig_SetInputOutputInputCallback(
+                    build_config,
+                    Some(input_callback),
+                ),
+            )?;
+            check(
+                snpe,
+                snpe.Snpe_BuildConfig_SetInputOutputCallback(build_config, Some(output_callback)),
+            )?;
+        }
+
+        if let Some(names) = output_names {
+            check(
+                snpe,
+                snpe.Snpe_BuildConfig_SetOutputBufferNames(build_config, names),
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Copies the strings out of a c string list and deletes it
+unsafe fn read_names(
+    snpe: &snpe_bindings::SNPE,
+    list: snpe_bindings::Snpe_StringList_Handle_t,
+) -> Vec<CString> {
+    if list.is_null() {
+        return vec![];
+    }
+
+    let names = (0..snpe.Snpe_StringList_Size(list))
+        .map(|i| CStr::from_ptr(snpe.Snpe_StringList_At(list, i)).to_owned())
+        .collect();
+
+    snpe.Snpe_StringList_Delete(list);
+    names
+}
+
+/// Reads the dimensions of the named inputs or outputs of the pool
+unsafe fn buffer_shapes(
+    snpe: &snpe_bindings::SNPE,
+    handle: snpe_bindings::Snpe_PSNPE_Handle_t,
+    names: &[CString],
+) -> HashMap<String, Vec<usize>> {
+    names
+        .iter()
+        .map(|name| {
+            (
+                name.to_string_lossy().to_string(),
+                buffer_shape(snpe, handle, name),
+            )
+        })
+        .collect()
+}
+
+/// Reads the dimensions of an input or output of the pool
+unsafe fn buffer_shape(
+    snpe: &snpe_bindings::SNPE,
+    handle: snpe_bindings::Snpe_PSNPE_Handle_t,
+    name: &CStr,
+) -> Vec<usize> {
+    let attributes = snpe.Snpe_PSNPE_GetInputOutputBufferAttributes(handle, name.as_ptr());
+    if attributes.is_null() {
+        return vec![];
+    }
+
+    let dims = snpe.Snpe_IBufferAttributes_GetDims(attributes);
+    let shape = read_shape(snpe, dims);
+    snpe.Snpe_TensorShape_Delete(dims);
+    snpe.Snpe_IBufferAttributes_Delete(attributes);
+
+    shape
+}
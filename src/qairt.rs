@@ -1,107 +1,485 @@
 /// Module for the Snapdragon QAIRT
-use std::{collections::HashMap, ffi::OsStr, path::PathBuf};
+mod backend;
+mod input;
 
-use tensor_rs::tensor::Tensor;
+use std::io::Read;
+use std::path::Path;
+use std::{collections::HashMap, ffi::OsStr, fs, path::PathBuf};
 
-enum ModelFormat {
+use log::warn;
+
+pub use self::backend::Backend;
+pub use self::input::{ModelInput, ModelOutput};
+use crate::dlcontainer::DlContainer;
+use crate::errors::Error;
+use crate::qnn::{
+    qnn_bindings, QnnBackend, QnnContext, QnnDataType, QnnDevice, QnnGraph, QnnLibrary,
+    QnnSystemLibrary, QnnTensorInfo,
+};
+use crate::snpe::{ElementType, Quantization, Snpe, SnpeTensor, TensorInfo};
+
+/// Error code of deviceCreate on a backend that can't create a device here
+const DEVICE_UNSUPPORTED: u32 =
+    qnn_bindings::QnnDevice_Error_t_QNN_DEVICE_ERROR_UNSUPPORTED_FEATURE as u32;
+
+/// Kind of file a model is stored in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ModelFormat {
+    /// SNPE container, run through SNPE
     Dlc,
+    /// QNN context binary from qnn-context-binary-generator
     Binary,
+    /// QNN model library from qnn-model-lib-generator, such as libmodel.so
+    Library,
+    /// Context serialized by the HTP backend, which only runs on the HTP
+    HtpCache,
+}
+
+/// Format of a model file, along with what was read to find it out
+struct Probe {
+    format: ModelFormat,
+    /// Contents of a file the system library read as a context binary, kept
+    /// so it isn't read again to load it
+    binary: Option<(Vec<u8>, QnnSystemLibrary)>,
+}
+
+impl ModelFormat {
+    /// Determines the format of a file from its first bytes, or by reading it
+    /// as a context binary with the QNN system library, falling back to its
+    /// extension when neither recognizes it
+    pub fn detect<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::probe(path.as_ref()).map(|probe| probe.format)
+    }
+
+    /// Same as detect, but hands over the contents of a context binary so
+    /// loading it doesn't read the file again
+    fn probe(path: &Path) -> Result<Probe, Error> {
+        let found = |format| Probe {
+            format,
+            binary: None,
+        };
+
+        // Files that can't be read are left to fail when loading
+        let mut header = Vec::with_capacity(4);
+        if let Ok(file) = fs::File::open(path) {
+            file.take(4).read_to_end(&mut header)?;
+        }
+
+        let extension = path.extension().and_then(|ext| ext.to_str());
+        if let Some(format) = Self::from_header(&header, None) {
+            return Ok(found(format));
+        }
+
+        // Context binaries have no fixed header, so ask the system library
+        // whether it can read one before trusting the extension
+        if let Ok(system) = QnnSystemLibrary::from_env() {
+            if let Ok(binary) = fs::read(path) {
+                if QnnContext::is_binary(&system, &binary) {
+                    return Ok(Probe {
+                        format: Self::from_binary_extension(extension),
+                        binary: Some((binary, system)),
+                    });
+                }
+            }
+        }
+
+        Self::from_header(&header, extension)
+            .map(found)
+            .ok_or_else(|| Error::UnsupportedFormat(path.display().to_string()))
+    }
+
+    /// Format of a file the system library read as a context binary. HTP
+    /// caches are context binaries too, told apart only by their extension.
+    fn from_binary_extension(extension: Option<&str>) -> Self {
+        match extension {
+            Some("cache") => ModelFormat::HtpCache,
+            _ => ModelFormat::Binary,
+        }
+    }
+
+    fn from_header(header: &[u8], extension: Option<&str>) -> Option<Self> {
+        match header {
+            // Containers are zip archives
+            [b'P', b'K', 3, 4, ..] => return Some(ModelFormat::Dlc),
+            // Elf, pe and mach-o shared libraries
+            [0x7f, b'E', b'L', b'F', ..] | [b'M', b'Z', ..] => return Some(ModelFormat::Library),
+            [0xcf, 0xfa, 0xed, 0xfe, ..] => return Some(ModelFormat::Library),
+            _ => {}
+        }
+
+        match extension? {
+            "dlc" => Some(ModelFormat::Dlc),
+            "bin" => Some(ModelFormat::Binary),
+            "so" | "dll" | "dylib" => Some(ModelFormat::Library),
+            "cache" => Some(ModelFormat::HtpCache),
+            _ => None,
+        }
+    }
+}
+
+/// Runs a loaded model on whichever backend it was loaded onto
+pub trait Executor {
+    /// Returns the names, shapes and types of the input tensors
+    fn input_tensors(&self) -> Result<Vec<TensorInfo>, Error>;
+
+    /// Returns the names, shapes and types of the output tensors
+    fn output_tensors(&self) -> Result<Vec<TensorInfo>, Error>;
+
+    /// Runs the model on the named input tensors, returning the named outputs
+    fn execute(
+        &self,
+        inputs: &HashMap<String, SnpeTensor>,
+    ) -> Result<HashMap<String, SnpeTensor>, Error>;
+}
+
+/// Executes a .dlc through SNPE
+struct DlcExecutor {
+    snpe: Snpe,
+    /// Kept alive for as long as the network built from it, and dropped after it
+    _container: DlContainer,
+}
+
+impl Executor for DlcExecutor {
+    fn input_tensors(&self) -> Result<Vec<TensorInfo>, Error> {
+        self.snpe.input_tensors()
+    }
+
+    fn output_tensors(&self) -> Result<Vec<TensorInfo>, Error> {
+        self.snpe.output_tensors()
+    }
+
+    fn execute(
+        &self,
+        inputs: &HashMap<String, SnpeTensor>,
+    ) -> Result<HashMap<String, SnpeTensor>, Error> {
+        self.snpe.execute_tensors(inputs)
+    }
+}
+
+/// Executes a QNN context binary
+struct ContextBinaryExecutor {
+    graph: QnnGraph,
+}
+
+impl Executor for ContextBinaryExecutor {
+    fn input_tensors(&self) -> Result<Vec<TensorInfo>, Error> {
+        Ok(self.graph.inputs().into_iter().map(tensor_info).collect())
+    }
+
+    fn output_tensors(&self) -> Result<Vec<TensorInfo>, Error> {
+        Ok(self.graph.outputs().into_iter().map(tensor_info).collect())
+    }
+
+    fn execute(
+        &self,
+        inputs: &HashMap<String, SnpeTensor>,
+    ) -> Result<HashMap<String, SnpeTensor>, Error> {
+        self.graph.execute(inputs)
+    }
+}
+
+/// Describes a QNN tensor the way SNPE does, so both executors look alike
+fn tensor_info(info: &QnnTensorInfo) -> TensorInfo {
+    let element_type = match info.data_type {
+        QnnDataType::Float32 => ElementType::Float,
+        QnnDataType::Float16 => ElementType::Float16,
+        QnnDataType::Uint8 => ElementType::Uint8,
+        QnnDataType::Uint16 => ElementType::Uint16,
+        QnnDataType::Int32 => ElementType::Int32,
+        QnnDataType::UFixedPoint8 => ElementType::Tf8,
+        QnnDataType::UFixedPoint16 => ElementType::Tf16,
+        QnnDataType::Other(_) => ElementType::Unknown,
+    };
+
+    TensorInfo {
+        name: info.name.clone(),
+        shape: info.shape.clone(),
+        element_type,
+        element_size: info.data_type.element_size().unwrap_or(0),
+        alignments: vec![1; info.shape.len()],
+        quantization: info.quantization.map(|q| Quantization {
+            step_exactly_0: (-q.offset).max(0) as u64,
+            step_size: q.scale,
+            delta: q.scale,
+            offset: q.offset as f32,
+        }),
+    }
 }
 
 /// Based on QairtModel in python api
-struct Model {
+pub struct Model {
     path: PathBuf,
-    backend: String,
-    // executor: Option<Box<dyn Executor>>,
+    backend: Backend,
+    executor: Option<Box<dyn Executor>>,
 }
 
 impl Model {
-    /// Creates a new QairtModel from the given path to a .dlc or .bin file
-    fn new<P>(path: &P, backend: String) -> Self
+    /// Creates a new QairtModel from the given path to a model in any ModelFormat
+    pub fn new<P>(path: &P, backend: Backend) -> Self
     where
         P: AsRef<OsStr>,
     {
         Self {
             path: PathBuf::from(path),
             backend,
-            // executor: None,
+            executor: None,
         }
     }
 
-    /// Determines the format of the model
-    fn format(&self) -> Result<ModelFormat, &str> {
-        match self.path.extension().map(|ext| ext.to_str()).flatten() {
-            Some("dlc") => Ok(ModelFormat::Dlc),
-            Some("bin") => Ok(ModelFormat::Binary),
-            _ => Err("Unsupported model format"),
-        }
+    /// Returns the backend the model runs on
+    pub fn backend(&self) -> &Backend {
+        &self.backend
+    }
+
+    /// Determines the format of the model from its contents, or its
+    /// extension when the contents don't tell
+    pub fn format(&self) -> Result<ModelFormat, Error> {
+        ModelFormat::detect(&self.path)
     }
 
     /// Determines if the model is loaded or not
-    fn is_loaded(&self) -> bool {
-        // self.executor.is_some()
-        false
+    pub fn is_loaded(&self) -> bool {
+        self.executor.is_some()
     }
 
-    fn load(&mut self) -> Result<(), &str> {
+    /// Returns the executor of a loaded model
+    pub fn executor(&self) -> Option<&dyn Executor> {
+        self.executor.as_deref()
+    }
+
+    /// Runs the loaded model. An array is bound to the sole input of the
+    /// model, a dict to the inputs by name and a path is read as tensor files.
+    pub fn run(&self, input: ModelInput) -> Result<ModelOutput, Error> {
+        let executor = self.executor().ok_or_else(|| {
+            Error::InvalidState("Model is not loaded. Call load() first".to_string())
+        })?;
+
+        let inputs = input.bind(&executor.input_tensors()?)?;
+        Ok(ModelOutput::new(executor.execute(&inputs)?))
+    }
+
+    /// Loads the model onto its backend. A .dlc is built into an SNPE
+    /// network running on the backend's runtime, the other formats are run
+    /// through the backend's QNN library.
+    pub fn load(&mut self) -> Result<(), Error> {
         if self.is_loaded() {
-            return Err("Model is already loaded. Call unload() first");
+            return Err(Error::InvalidState(
+                "Model is already loaded. Call unload() first".to_string(),
+            ));
         }
 
-        // todo: load model
-        // self.executor = Some(Box::new());
+        let probe = ModelFormat::probe(&self.path)?;
+        let executor = match probe.format {
+            ModelFormat::Dlc => self.load_dlc()?,
+            ModelFormat::Binary => self.load_context_binary(probe.binary)?,
+            ModelFormat::Library => self.load_model_library()?,
+            ModelFormat::HtpCache => self.load_htp_cache(probe.binary)?,
+        };
 
+        self.executor = Some(executor);
         Ok(())
     }
 
-    fn unload(&mut self) -> Result<(), &str> {
+    /// Frees the executor and everything the backend allocated for the model
+    pub fn unload(&mut self) -> Result<(), Error> {
         if !self.is_loaded() {
-            return Err("Model is not loaded");
+            return Err(Error::InvalidState("Model is not loaded".to_string()));
         }
 
-        // todo: unload model
-        // self.executor = None;
-
+        self.executor = None;
         Ok(())
     }
+
+    /// Builds an SNPE network from the container, running on the backend's runtime
+    fn load_dlc(&self) -> Result<Box<dyn Executor>, Error> {
+        let runtime = self.backend.runtime().ok_or_else(|| {
+            Error::InvalidArgument(format!(
+                "The {} backend can't run .dlc models",
+                self.backend
+            ))
+        })?;
+        let path = self.path.to_str().ok_or_else(|| {
+            Error::InvalidArgument(format!("Path {} isn't valid utf-8", self.path.display()))
+        })?;
+        let container = DlContainer::from_path(path)?;
+        let snpe = Snpe::builder(&container)
+            .runtime_order(&[runtime])
+            .build()?;
+
+        Ok(Box::new(DlcExecutor {
+            snpe,
+            _container: container,
+        }))
+    }
+
+    /// Restores a QNN context binary on the backend's QNN library
+    fn load_context_binary(
+        &self,
+        probed: Option<(Vec<u8>, QnnSystemLibrary)>,
+    ) -> Result<Box<dyn Executor>, Error> {
+        let (backend, device) = self.qnn_backend()?;
+
+        // Files detected by their extension haven't been read yet
+        let (binary, system) = match probed {
+            Some(probed) => probed,
+            None => (fs::read(&self.path)?, QnnSystemLibrary::from_env()?),
+        };
+        let context = QnnContext::from_binary(&backend, device.as_ref(), &system, &binary)?;
+        self.single_graph(&context)
+    }
+
+    /// Restores a serialized HTP context, which is a context binary only the
+    /// HTP backend can read
+    fn load_htp_cache(
+        &self,
+        probed: Option<(Vec<u8>, QnnSystemLibrary)>,
+    ) -> Result<Box<dyn Executor>, Error> {
+        if !matches!(self.backend, Backend::Htp | Backend::Custom(_)) {
+            return Err(Error::InvalidArgument(format!(
+                "HTP caches only run on the htp backend, not {}",
+                self.backend
+            )));
+        }
+
+        self.load_context_binary(probed)
+    }
+
+    /// Composes the graphs of a QNN model library on the backend's QNN library
+    fn load_model_library(&self) -> Result<Box<dyn Executor>, Error> {
+        let (backend, device) = self.qnn_backend()?;
+
+        // Bare file names would be looked up on the library search path
+        let path = match self.path.parent() {
+            Some(parent) if parent.as_os_str().is_empty() => Path::new(".").join(&self.path),
+            _ => self.path.clone(),
+        };
+
+        let context = QnnContext::from_model_library(&backend, device.as_ref(), path)?;
+        self.single_graph(&context)
+    }
+
+    /// Creates a backend on the QNN library, with a device if it manages them
+    fn qnn_backend(&self) -> Result<(QnnBackend, Option<QnnDevice>), Error> {
+        let library = QnnLibrary::open(self.backend.qnn_library())?;
+        let backend = QnnBackend::new(&library)?;
+        let device = match backend.has_devices() {
+            true => match QnnDevice::new(&backend) {
+                Ok(device) => Some(device),
+                // Backends with devices may still not have one on this platform
+                Err(Error::Qnn(details)) if details.code == DEVICE_UNSUPPORTED => None,
+                Err(e) => return Err(e),
+            },
+            false => None,
+        };
+
+        Ok((backend, device))
+    }
+
+    /// Wraps the sole graph of a context in an executor
+    fn single_graph(&self, context: &QnnContext) -> Result<Box<dyn Executor>, Error> {
+        let names = context.graph_names();
+        let [name] = names[..] else {
+            return Err(Error::UnsupportedFormat(format!(
+                "{} holds graphs {:?} but a model has exactly one",
+                self.path.display(),
+                names
+            )));
+        };
+
+        // The graph keeps the context alive
+        let graph = context.graph(name)?;
+        Ok(Box::new(ContextBinaryExecutor { graph }))
+    }
 }
 
 impl Drop for Model {
     fn drop(&mut self) {
         if self.is_loaded() {
-            self.unload().expect("Unable to unload model")
+            if let Err(e) = self.unload() {
+                warn!(target: "Model", "Unable to unload model: {}", e);
+            }
         }
     }
 }
 
-struct ModelInput {
-    string: Option<String>,
-    array: Option<Tensor>,
-    dict: Option<HashMap<String, Tensor>>,
-}
+#[cfg(test)]
+mod tests {
+    use super::{Backend, Model, ModelFormat, ModelInput};
+    use crate::errors::Error;
 
-impl ModelInput {
-    fn from_str(s: &str) -> Self {
-        Self {
-            string: Some(s.to_string()),
-            array: None,
-            dict: None,
-        }
+    #[test]
+    fn test_load_dlc() {
+        let mut model = Model::new(&"test/data/resnet50.dlc", Backend::Cpu);
+        assert!(!model.is_loaded());
+
+        model.load().unwrap();
+        assert!(model.is_loaded());
+
+        let executor = model.executor().unwrap();
+        assert_eq!(executor.input_tensors().unwrap().len(), 1);
+
+        let image = (vec![0.0; 224 * 224 * 3], vec![224, 224, 3]);
+        let output = model.run(ModelInput::from_array(image).unwrap()).unwrap();
+        assert_eq!(output.single().unwrap().num_elements(), 1000);
+
+        assert!(matches!(model.load(), Err(Error::InvalidState(_))));
+
+        model.unload().unwrap();
+        assert!(!model.is_loaded());
+        assert!(matches!(model.unload(), Err(Error::InvalidState(_))));
     }
 
-    fn from_array(array: Tensor) -> Self {
-        Self {
-            string: None,
-            array: Some(array),
-            dict: None,
-        }
+    #[test]
+    fn format_from_header() {
+        let zip = b"PK\x03\x04";
+        assert_eq!(
+            ModelFormat::from_header(zip, Some("bin")),
+            Some(ModelFormat::Dlc)
+        );
+        assert_eq!(ModelFormat::from_header(zip, None), Some(ModelFormat::Dlc));
+
+        let elf = b"\x7fELF";
+        assert_eq!(
+            ModelFormat::from_header(elf, Some("model")),
+            Some(ModelFormat::Library)
+        );
+
+        let opaque = [0x12, 0x34, 0x56, 0x78];
+        assert_eq!(
+            ModelFormat::from_header(&opaque, Some("bin")),
+            Some(ModelFormat::Binary)
+        );
+        assert_eq!(
+            ModelFormat::from_header(&opaque, Some("cache")),
+            Some(ModelFormat::HtpCache)
+        );
+        assert_eq!(ModelFormat::from_header(&opaque, Some("onnx")), None);
+        assert_eq!(
+            ModelFormat::from_header(&[], Some("dlc")),
+            Some(ModelFormat::Dlc)
+        );
     }
 
-    fn from_dict(dict: HashMap<String, Tensor>) -> Self {
-        Self {
-            string: None,
-            array: None,
-            dict: Some(dict),
-        }
+    #[test]
+    fn binary_extension() {
+        assert_eq!(
+            ModelFormat::from_binary_extension(Some("cache")),
+            ModelFormat::HtpCache
+        );
+        assert_eq!(
+            ModelFormat::from_binary_extension(Some("dlc")),
+            ModelFormat::Binary
+        );
+        assert_eq!(
+            ModelFormat::from_binary_extension(None),
+            ModelFormat::Binary
+        );
+    }
+
+    #[test]
+    fn unknown_format() {
+        let mut model = Model::new(&"model.onnx", Backend::Cpu);
+        assert!(matches!(model.load(), Err(Error::UnsupportedFormat(_))));
+        assert!(!model.is_loaded());
     }
 }
@@ -0,0 +1,161 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::errors::Error;
+use crate::snpe::Runtime;
+
+/// Hardware a model runs on, along with the libraries that drive it
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Backend {
+    /// Cpu reference backend
+    Cpu,
+    /// Adreno gpu
+    Gpu,
+    /// Adreno gpu with 16 bit float math, which SNPE runs as its own runtime
+    GpuFloat16,
+    /// Hexagon tensor processor
+    Htp,
+    /// Hexagon dsp on chips without an htp
+    Dsp,
+    /// Records the QNN api calls instead of executing them
+    Saver,
+    /// QNN backend library at the given path
+    Custom(PathBuf),
+}
+
+impl Backend {
+    /// Returns the SNPE runtime that runs .dlc models on this backend, if any
+    pub fn runtime(&self) -> Option<Runtime> {
+        match self {
+            Backend::Cpu => Some(Runtime::CPU),
+            Backend::Gpu => Some(Runtime::GPU),
+            Backend::GpuFloat16 => Some(Runtime::GpuFloat16),
+            // SNPE drives the htp through its dsp runtime
+            Backend::Htp | Backend::Dsp => Some(Runtime::DSP),
+            Backend::Saver | Backend::Custom(_) => None,
+        }
+    }
+
+    /// Returns the QNN backend library, as a file name to be found on the
+    /// library search path or the path of a custom library
+    pub fn qnn_library(&self) -> PathBuf {
+        let name = match self {
+            Backend::Cpu => "QnnCpu",
+            Backend::Gpu | Backend::GpuFloat16 => "QnnGpu",
+            Backend::Htp => "QnnHtp",
+            Backend::Dsp => "QnnDsp",
+            Backend::Saver => "QnnSaver",
+            Backend::Custom(path) => return path.clone(),
+        };
+
+        PathBuf::from(library_file(name))
+    }
+
+    /// Returns the name used in config files
+    pub fn name(&self) -> &str {
+        match self {
+            Backend::Cpu => "cpu",
+            Backend::Gpu => "gpu",
+            Backend::GpuFloat16 => "gpu_float16",
+            Backend::Htp => "htp",
+            Backend::Dsp => "dsp",
+            Backend::Saver => "saver",
+            Backend::Custom(path) => path.to_str().unwrap_or("custom"),
+        }
+    }
+}
+
+/// Returns the platform's file name for a shared library
+fn library_file(name: &str) -> String {
+    if cfg!(windows) {
+        format!("{}.dll", name)
+    } else {
+        format!("lib{}.so", name)
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Backend::Custom(path) => write!(f, "{}", path.display()),
+            backend => f.write_str(backend.name()),
+        }
+    }
+}
+
+impl FromStr for Backend {
+    type Err = Error;
+
+    /// Parses the backend names, the SNPE runtime names that map to them, or
+    /// the path of a custom backend library
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "cpu" | "cpu_float32" => return Ok(Backend::Cpu),
+            "gpu" | "gpu_float32_16_hybrid" => return Ok(Backend::Gpu),
+            "gpu_float16" => return Ok(Backend::GpuFloat16),
+            "htp" => return Ok(Backend::Htp),
+            "dsp" | "dsp_fixed8_tf" => return Ok(Backend::Dsp),
+            "saver" => return Ok(Backend::Saver),
+            _ => {}
+        }
+
+        let path = Path::new(s);
+        let is_library = matches!(
+            path.extension().and_then(|ext| ext.to_str()),
+            Some("so") | Some("dll")
+        );
+
+        if is_library || path.components().count() > 1 {
+            return Ok(Backend::Custom(path.to_path_buf()));
+        }
+
+        Err(Error::InvalidArgument(format!("Unknown backend {}", s)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::Backend;
+    use crate::snpe::Runtime;
+
+    #[test]
+    fn parse_backends() {
+        assert_eq!("HTP".parse::<Backend>().unwrap(), Backend::Htp);
+        assert_eq!(
+            "gpu_float16".parse::<Backend>().unwrap(),
+            Backend::GpuFloat16
+        );
+        assert_eq!(
+            "/opt/qnn/libQnnCustom.so".parse::<Backend>().unwrap(),
+            Backend::Custom(PathBuf::from("/opt/qnn/libQnnCustom.so"))
+        );
+        assert!("tpu".parse::<Backend>().is_err());
+
+        for backend in [
+            Backend::Cpu,
+            Backend::Gpu,
+            Backend::GpuFloat16,
+            Backend::Htp,
+            Backend::Dsp,
+            Backend::Saver,
+        ] {
+            assert_eq!(backend.name().parse::<Backend>().unwrap(), backend);
+        }
+    }
+
+    #[test]
+    fn backend_libraries() {
+        assert_eq!(Backend::Htp.runtime(), Some(Runtime::DSP));
+        assert_eq!(Backend::GpuFloat16.runtime(), Some(Runtime::GpuFloat16));
+        assert_eq!(Backend::Saver.runtime(), None);
+
+        let library = Backend::Cpu.qnn_library();
+        assert!(library.to_str().unwrap().contains("QnnCpu"));
+
+        let custom = PathBuf::from("backends/libQnnCustom.so");
+        assert_eq!(Backend::Custom(custom.clone()).qnn_library(), custom);
+    }
+}
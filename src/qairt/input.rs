@@ -0,0 +1,529 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::errors::Error;
+use crate::snpe::{AsSnpeTensor, ElementType, FromSnpeTensor, SnpeTensor, TensorData, TensorInfo};
+
+/// Input to a model run
+pub enum ModelInput {
+    /// Path to a .raw or .npy file for a model with a single input, or to an
+    /// snpe-net-run style input list whose line names a file per input. Like
+    /// snpe-net-run, .raw files hold float32 data.
+    Path(PathBuf),
+    /// Same as Path, but .raw files hold the element type the model takes,
+    /// like snpe-net-run with --use_native_input_files. Fixed point values
+    /// are dequantized with the input's quantization parameters.
+    NativePath(PathBuf),
+    /// Tensor bound to the sole input of the model
+    Array(SnpeTensor),
+    /// Tensors bound to the inputs of the model by name
+    Dict(HashMap<String, SnpeTensor>),
+}
+
+impl ModelInput {
+    pub fn from_str(s: &str) -> Self {
+        ModelInput::Path(PathBuf::from(s))
+    }
+
+    /// Creates an input from a single tensor of any supported type
+    pub fn from_array<T>(array: T) -> Result<Self, Error>
+    where
+        T: AsSnpeTensor,
+    {
+        Ok(ModelInput::Array(array.to_snpe_tensor()?))
+    }
+
+    /// Creates an input from named tensors of any supported type
+    pub fn from_dict<T>(dict: HashMap<String, T>) -> Result<Self, Error>
+    where
+        T: AsSnpeTensor,
+    {
+        let dict = dict
+            .into_iter()
+            .map(|(name, tensor)| Ok((name, tensor.to_snpe_tensor()?)))
+            .collect::<Result<_, Error>>()?;
+
+        Ok(ModelInput::Dict(dict))
+    }
+
+    /// Binds the input to the inputs of a model, reshaping tensors whose
+    /// element count matches to the shapes the model expects
+    pub(crate) fn bind(self, inputs: &[TensorInfo]) -> Result<HashMap<String, SnpeTensor>, Error> {
+        match self {
+            ModelInput::Path(path) => read_input(&path, inputs, false)?.bind(inputs),
+            ModelInput::NativePath(path) => read_input(&path, inputs, true)?.bind(inputs),
+            ModelInput::Array(tensor) => {
+                let info = sole_input(inputs)?;
+                Ok(HashMap::from([(info.name.clone(), fit(info, tensor)?)]))
+            }
+            ModelInput::Dict(mut tensors) => {
+                if let Some(name) = tensors
+                    .keys()
+                    .find(|name| !inputs.iter().any(|info| &info.name == *name))
+                {
+                    return Err(Error::InvalidArgument(format!(
+                        "Unknown input tensor {}, the model takes {:?}",
+                        name,
+                        input_names(inputs)
+                    )));
+                }
+
+                inputs
+                    .iter()
+                    .map(|info| {
+                        let tensor = tensors.remove(&info.name).ok_or_else(|| {
+                            Error::InvalidArgument(format!("Missing input tensor {}", info.name))
+                        })?;
+
+                        Ok((info.name.clone(), fit(info, tensor)?))
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Named output tensors of a model run
+pub struct ModelOutput {
+    tensors: HashMap<String, SnpeTensor>,
+}
+
+impl ModelOutput {
+    pub(crate) fn new(tensors: HashMap<String, SnpeTensor>) -> Self {
+        Self { tensors }
+    }
+
+    /// Returns the output tensor with the given name
+    pub fn get(&self, name: &str) -> Option<&SnpeTensor> {
+        self.tensors.get(name)
+    }
+
+    /// Returns the output of a model with a single output
+    pub fn single(&self) -> Option<&SnpeTensor> {
+        match self.tensors.len() {
+            1 => self.tensors.values().next(),
+            _ => None,
+        }
+    }
+
+    /// Returns the names of the outputs
+    pub fn names(&self) -> Vec<&str> {
+        self.tensors.keys().map(String::as_str).collect()
+    }
+
+    /// Returns the number of outputs
+    pub fn len(&self) -> usize {
+        self.tensors.len()
+    }
+
+    /// Returns if there are no outputs
+    pub fn is_empty(&self) -> bool {
+        self.tensors.is_empty()
+    }
+
+    /// Consumes the output, returning the tensors by name
+    pub fn into_tensors(self) -> HashMap<String, SnpeTensor> {
+        self.tensors
+    }
+
+    /// Converts every output into another tensor type
+    pub fn convert<O>(self) -> Result<HashMap<String, O>, Error>
+    where
+        O: FromSnpeTensor,
+    {
+        self.tensors
+            .into_iter()
+            .map(|(name, tensor)| Ok((name, O::from_snpe_tensor(tensor)?)))
+            .collect()
+    }
+}
+
+/// Returns the only input of a model, failing if there are several
+fn sole_input(inputs: &[TensorInfo]) -> Result<&TensorInfo, Error> {
+    match inputs {
+        [info] => Ok(info),
+        _ => Err(Error::InvalidArgument(format!(
+            "A single tensor can only be bound to a model with one input, this one takes {:?}",
+            input_names(inputs)
+        ))),
+    }
+}
+
+fn input_names(inputs: &[TensorInfo]) -> Vec<&str> {
+    inputs.iter().map(|info| info.name.as_str()).collect()
+}
+
+/// Reshapes a tensor to the shape the model expects for the input
+fn fit(info: &TensorInfo, tensor: SnpeTensor) -> Result<SnpeTensor, Error> {
+    if tensor.num_elements() != info.num_elements() {
+        return Err(Error::ShapeMismatch(format!(
+            "Input {} expects shape {:?} but got {:?}",
+            info.name,
+            info.shape,
+            tensor.shape()
+        )));
+    }
+
+    SnpeTensor::new(&info.shape, tensor.into_data())
+}
+
+/// Reads the tensor files named by a path input
+fn read_input(path: &Path, inputs: &[TensorInfo], native: bool) -> Result<ModelInput, Error> {
+    if is_tensor_file(path) {
+        let info = sole_input(inputs)?;
+        return Ok(ModelInput::Array(read_tensor(path, info, native)?));
+    }
+
+    // Input lists start with an optional #output names line
+    let list = fs::read_to_string(path)?;
+    let lines: Vec<&str> = list
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+
+    let [line] = lines[..] else {
+        return Err(Error::InvalidArgument(format!(
+            "Input list {} has {} entries but a run takes exactly one",
+            path.display(),
+            lines.len()
+        )));
+    };
+
+    // A line is either a single file for the sole input or name:=file pairs
+    if !line.contains(":=") {
+        let info = sole_input(inputs)?;
+        return Ok(ModelInput::Array(read_tensor(
+            Path::new(line),
+            info,
+            native,
+        )?));
+    }
+
+    let mut tensors = HashMap::new();
+    for entry in line.split_whitespace() {
+        let Some((name, file)) = entry.split_once(":=") else {
+            return Err(Error::InvalidArgument(format!(
+                "Input list entry {} isn't of the form name:=file",
+                entry
+            )));
+        };
+
+        let info = inputs
+            .iter()
+            .find(|info| info.name == name)
+            .ok_or_else(|| {
+                Error::InvalidArgument(format!(
+                    "Unknown input tensor {}, the model takes {:?}",
+                    name,
+                    input_names(inputs)
+                ))
+            })?;
+
+        tensors.insert(
+            name.to_string(),
+            read_tensor(Path::new(file), info, native)?,
+        );
+    }
+
+    Ok(ModelInput::Dict(tensors))
+}
+
+fn is_tensor_file(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("raw") | Some("npy")
+    )
+}
+
+/// Reads a .npy file, or a .raw file holding the input's elements as
+/// float32, or in the model's element type if they're native. Native fixed
+/// point values are dequantized, since the network takes the floats they
+/// stand for.
+fn read_tensor(path: &Path, info: &TensorInfo, native: bool) -> Result<SnpeTensor, Error> {
+    let bytes = fs::read(path)?;
+
+    let tensor = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("npy") => parse_npy(&bytes),
+        _ if !native => SnpeTensor::from_bytes(&info.shape, ElementType::Float, &bytes),
+        _ => match info.element_type {
+            ElementType::Tf8 => read_quantized(info, ElementType::Uint8, &bytes),
+            ElementType::Tf16 => read_quantized(info, ElementType::Uint16, &bytes),
+            ElementType::Uint8 | ElementType::Unsigned8Bit => {
+                SnpeTensor::from_bytes(&info.shape, ElementType::Uint8, &bytes)
+            }
+            ElementType::Uint16 => SnpeTensor::from_bytes(&info.shape, ElementType::Uint16, &bytes),
+            ElementType::Int32 => SnpeTensor::from_bytes(&info.shape, ElementType::Int32, &bytes),
+            _ => SnpeTensor::from_bytes(&info.shape, ElementType::Float, &bytes),
+        },
+    };
+
+    tensor.map_err(|error| {
+        Error::InvalidArgument(format!(
+            "Can't read input {} from {}: {}",
+            info.name,
+            path.display(),
+            error
+        ))
+    })
+}
+
+/// Reads fixed point values of the given width and dequantizes them with
+/// the input's quantization parameters
+fn read_quantized(
+    info: &TensorInfo,
+    element_type: ElementType,
+    bytes: &[u8],
+) -> Result<SnpeTensor, Error> {
+    let quantization = info.quantization.ok_or_else(|| {
+        Error::InvalidArgument(format!(
+            "Input {} has no quantization parameters",
+            info.name
+        ))
+    })?;
+
+    let values = SnpeTensor::from_bytes(&info.shape, element_type, bytes)?
+        .to_f32()
+        .into_iter()
+        .map(|value| quantization.dequantize(value))
+        .collect();
+
+    SnpeTensor::new(&info.shape, TensorData::Float(values))
+}
+
+/// Parses a little endian, C ordered numpy array
+fn parse_npy(bytes: &[u8]) -> Result<SnpeTensor, Error> {
+    let invalid = || Error::UnsupportedFormat("not a valid .npy file".to_string());
+
+    if bytes.len() < 10 || &bytes[..6] != b"\x93NUMPY" {
+        return Err(invalid());
+    }
+
+    // Version 1 has a 2 byte header length, later versions a 4 byte one
+    let (header_start, header_len) = match bytes[6] {
+        1 => (10, u16::from_le_bytes([bytes[8], bytes[9]]) as usize),
+        _ if bytes.len() >= 12 => (
+            12,
+            u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize,
+        ),
+        _ => return Err(invalid()),
+    };
+
+    let data_start = header_start + header_len;
+    let header = bytes
+        .get(header_start..data_start)
+        .ok_or_else(invalid)
+        .and_then(|header| Ok(std::str::from_utf8(header)?))?;
+
+    if header.contains("'fortran_order': True") {
+        return Err(Error::UnsupportedFormat(
+            ".npy arrays in fortran order".to_string(),
+        ));
+    }
+
+    let element_type = match header_value(header, "descr").ok_or_else(invalid)? {
+        "'<f4'" => ElementType::Float,
+        "'|u1'" | "'<u1'" => ElementType::Uint8,
+        "'<u2'" => ElementType::Uint16,
+        "'<i4'" => ElementType::Int32,
+        descr => {
+            return Err(Error::UnsupportedElementType(format!(
+                ".npy arrays of {}",
+                descr
+            )))
+        }
+    };
+
+    let shape = header_value(header, "shape")
+        .ok_or_else(invalid)?
+        .trim_matches(|c| c == '(' || c == ')')
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        .map(|dim| dim.parse::<usize>().map_err(|_| invalid()))
+        .collect::<Result<Vec<usize>, Error>>()?;
+
+    SnpeTensor::from_bytes(&shape, element_type, &bytes[data_start..])
+}
+
+/// Returns the raw value of a key in the python dict literal of a .npy header
+fn header_value<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    let start = header.find(&format!("'{}':", key))? + key.len() + 3;
+    let rest = header[start..].trim_start();
+
+    // Tuples contain commas, so they end at the closing parenthesis
+    let end = match rest.starts_with('(') {
+        true => rest.find(')')? + 1,
+        false => rest.find(|c| c == ',' || c == '}')?,
+    };
+
+    Some(rest[..end].trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::fs;
+
+    use super::{parse_npy, ModelInput};
+    use crate::errors::Error;
+    use crate::snpe::{ElementType, Quantization, SnpeTensor, TensorData, TensorInfo};
+
+    fn info(name: &str, shape: &[usize]) -> TensorInfo {
+        TensorInfo {
+            name: name.to_string(),
+            shape: shape.to_vec(),
+            element_type: ElementType::Float,
+            element_size: 4,
+            alignments: vec![0; shape.len()],
+            quantization: None,
+        }
+    }
+
+    fn npy(header: &str, data: &[u8]) -> Vec<u8> {
+        let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+        bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(header.as_bytes());
+        bytes.extend_from_slice(data);
+        bytes
+    }
+
+    #[test]
+    fn array_binds_to_sole_input() {
+        let tensor = SnpeTensor::new(&[4], TensorData::Float(vec![0.0; 4])).unwrap();
+        let inputs = ModelInput::Array(tensor)
+            .bind(&[info("image", &[1, 2, 2])])
+            .unwrap();
+        assert_eq!(inputs["image"].shape(), &[1, 2, 2]);
+
+        let tensor = SnpeTensor::new(&[4], TensorData::Float(vec![0.0; 4])).unwrap();
+        let result = ModelInput::Array(tensor).bind(&[info("a", &[4]), info("b", &[4])]);
+        assert!(matches!(result, Err(Error::InvalidArgument(_))));
+    }
+
+    #[test]
+    fn dict_errors_name_the_tensor() {
+        let inputs = [info("a", &[2]), info("b", &[3])];
+        let tensor = |len| SnpeTensor::new(&[len], TensorData::Float(vec![0.0; len])).unwrap();
+
+        let missing = ModelInput::Dict(HashMap::from([("a".to_string(), tensor(2))]));
+        match missing.bind(&inputs) {
+            Err(Error::InvalidArgument(message)) => assert!(message.contains("b")),
+            _ => panic!("expected a missing input error"),
+        }
+
+        let mismatched = ModelInput::Dict(HashMap::from([
+            ("a".to_string(), tensor(2)),
+            ("b".to_string(), tensor(4)),
+        ]));
+        match mismatched.bind(&inputs) {
+            Err(Error::ShapeMismatch(message)) => assert!(message.starts_with("Input b ")),
+            _ => panic!("expected a shape mismatch"),
+        }
+
+        let unknown = ModelInput::Dict(HashMap::from([("c".to_string(), tensor(2))]));
+        assert!(matches!(
+            unknown.bind(&inputs),
+            Err(Error::InvalidArgument(message)) if message.contains("c")
+        ));
+    }
+
+    #[test]
+    fn input_list_names_files() {
+        let dir = std::env::temp_dir().join(format!("snpe-input-list-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let a = dir.join("a.raw");
+        let b = dir.join("b.npy");
+        fs::write(&a, [1.0f32, 2.0].map(f32::to_ne_bytes).concat()).unwrap();
+        fs::write(
+            &b,
+            npy(
+                "{'descr': '<f4', 'fortran_order': False, 'shape': (3,), }\n",
+                &[0u8; 12],
+            ),
+        )
+        .unwrap();
+
+        let list = dir.join("input_list.txt");
+        fs::write(
+            &list,
+            format!("#output\na:={} b:={}\n", a.display(), b.display()),
+        )
+        .unwrap();
+
+        let inputs = ModelInput::Path(list)
+            .bind(&[info("a", &[1, 2]), info("b", &[3])])
+            .unwrap();
+        assert_eq!(inputs["a"].data(), &TensorData::Float(vec![1.0, 2.0]));
+        assert_eq!(inputs["b"].shape(), &[3]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn raw_files_are_float_unless_native() {
+        let dir = std::env::temp_dir().join(format!("snpe-raw-input-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let quantized = [TensorInfo {
+            element_type: ElementType::Tf8,
+            element_size: 1,
+            quantization: Some(Quantization {
+                step_exactly_0: 2,
+                step_size: 0.5,
+                delta: 0.5,
+                offset: -2.0,
+            }),
+            ..info("image", &[4])
+        }];
+
+        let floats = dir.join("floats.raw");
+        fs::write(&floats, [0.5f32; 4].map(f32::to_ne_bytes).concat()).unwrap();
+        let inputs = ModelInput::Path(floats).bind(&quantized).unwrap();
+        assert_eq!(inputs["image"].data(), &TensorData::Float(vec![0.5; 4]));
+
+        let bytes = dir.join("bytes.raw");
+        fs::write(&bytes, [1u8, 2, 3, 4]).unwrap();
+        let inputs = ModelInput::NativePath(bytes.clone())
+            .bind(&quantized)
+            .unwrap();
+        // The network is handed floats, so the codes arrive dequantized
+        assert_eq!(inputs["image"].to_f32(), vec![-0.5, 0.0, 0.5, 1.0]);
+
+        let unquantized = [TensorInfo {
+            quantization: None,
+            ..quantized[0].clone()
+        }];
+        assert!(ModelInput::NativePath(bytes.clone())
+            .bind(&unquantized)
+            .is_err());
+
+        // Four bytes are a single float, not the four elements the input takes
+        assert!(ModelInput::Path(bytes).bind(&quantized).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn npy_arrays() {
+        let bytes = npy(
+            "{'descr': '|u1', 'fortran_order': False, 'shape': (2, 3), }\n",
+            &[1, 2, 3, 4, 5, 6],
+        );
+        let tensor = parse_npy(&bytes).unwrap();
+        assert_eq!(tensor.shape(), &[2, 3]);
+        assert_eq!(tensor.data(), &TensorData::Uint8(vec![1, 2, 3, 4, 5, 6]));
+
+        let doubles = npy(
+            "{'descr': '<f8', 'fortran_order': False, 'shape': (1,), }\n",
+            &[0; 8],
+        );
+        assert!(matches!(
+            parse_npy(&doubles),
+            Err(Error::UnsupportedElementType(_))
+        ));
+        assert!(parse_npy(b"not numpy").is_err());
+    }
+}
@@ -0,0 +1,48 @@
+/// Module for QNN backends, used to run context binaries built by
+/// qnn-context-binary-generator and model libraries built by
+/// qnn-model-lib-generator
+mod backend;
+mod context;
+mod library;
+mod model;
+mod tensor;
+
+use semver::Version;
+
+pub use self::backend::{QnnBackend, QnnDevice};
+pub use self::context::{QnnContext, QnnGraph};
+pub use self::library::{QnnLibrary, QnnSystemLibrary};
+pub use self::tensor::{QnnDataType, QnnTensorInfo};
+use crate::errors::{Error, ErrorDetails};
+
+pub mod qnn_bindings {
+    include!(concat!(env!("OUT_DIR"), "/qnn_bindings.rs"));
+}
+
+/// Turns the result of a QNN call into an error naming the call
+fn check(result: qnn_bindings::Qnn_ErrorHandle_t, call: &str) -> Result<(), Error> {
+    if result == qnn_bindings::QNN_SUCCESS as qnn_bindings::Qnn_ErrorHandle_t {
+        return Ok(());
+    }
+
+    // The low 16 bits hold the error code, the rest identify the api that raised it
+    Err(Error::Qnn(ErrorDetails {
+        code: (result & 0xffff) as u32,
+        message: format!("{} failed", call),
+        info: String::new(),
+    }))
+}
+
+/// Returns a function from a backend's table, which leaves out anything the
+/// backend doesn't implement
+fn function<F>(function: Option<F>, name: &str) -> Result<F, Error> {
+    function.ok_or_else(|| Error::InvalidState(format!("QNN backend doesn't implement {}", name)))
+}
+
+fn to_version(version: &qnn_bindings::Qnn_Version_t) -> Version {
+    Version::new(
+        version.major as u64,
+        version.minor as u64,
+        version.patch as u64,
+    )
+}
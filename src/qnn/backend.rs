@@ -0,0 +1,136 @@
+use std::ptr;
+use std::sync::Arc;
+
+use log::warn;
+
+use super::library::QnnLibrary;
+use super::qnn_bindings::{
+    QnnProperty_Error_t_QNN_PROPERTY_SUPPORTED, QnnProperty_Key_t, Qnn_BackendHandle_t,
+    Qnn_DeviceHandle_t, Qnn_ErrorHandle_t, QNN_PROPERTY_GROUP_DEVICE,
+};
+use super::{check, function};
+use crate::errors::Error;
+
+/// Backend created from a QNN backend library. Clones share the backend.
+#[derive(Clone)]
+pub struct QnnBackend {
+    inner: Arc<BackendHandle>,
+}
+
+/// Device of a backend, for backends that have them. Clones share the device.
+#[derive(Clone)]
+pub struct QnnDevice {
+    inner: Arc<DeviceHandle>,
+}
+
+struct BackendHandle {
+    handle: Qnn_BackendHandle_t,
+    library: QnnLibrary,
+}
+
+struct DeviceHandle {
+    handle: Qnn_DeviceHandle_t,
+    /// Devices are freed before the backend they were created on
+    backend: QnnBackend,
+}
+
+impl QnnBackend {
+    /// Creates a backend with the default configuration
+    pub fn new(library: &QnnLibrary) -> Result<Self, Error> {
+        let create = function(library.interface().backendCreate, "backendCreate")?;
+
+        let mut handle = ptr::null_mut();
+        unsafe {
+            check(
+                create(ptr::null_mut(), ptr::null_mut(), &mut handle),
+                "backendCreate",
+            )?;
+        }
+
+        Ok(Self {
+            inner: Arc::new(BackendHandle {
+                handle,
+                library: library.clone(),
+            }),
+        })
+    }
+
+    /// Returns the library the backend was created from
+    pub fn library(&self) -> &QnnLibrary {
+        &self.inner.library
+    }
+
+    /// Returns the internal handle to the c object
+    pub(crate) fn handle(&self) -> Qnn_BackendHandle_t {
+        self.inner.handle
+    }
+
+    /// Returns if the backend manages devices, which only some backends do
+    pub fn has_devices(&self) -> bool {
+        let interface = self.library().interface();
+        let (Some(has_capability), Some(_)) =
+            (interface.propertyHasCapability, interface.deviceCreate)
+        else {
+            return false;
+        };
+
+        let result = unsafe { has_capability(QNN_PROPERTY_GROUP_DEVICE as QnnProperty_Key_t) };
+        result == QnnProperty_Error_t_QNN_PROPERTY_SUPPORTED as Qnn_ErrorHandle_t
+    }
+}
+
+impl Drop for BackendHandle {
+    fn drop(&mut self) {
+        let Some(free) = self.library.interface().backendFree else {
+            return;
+        };
+
+        if let Err(e) = check(unsafe { free(self.handle) }, "backendFree") {
+            warn!(target: "QnnBackend", "Error cleaning up backend: {}", e);
+        }
+    }
+}
+
+impl QnnDevice {
+    /// Creates a device on the backend with the default configuration
+    pub fn new(backend: &QnnBackend) -> Result<Self, Error> {
+        let create = function(backend.library().interface().deviceCreate, "deviceCreate")?;
+
+        let mut handle = ptr::null_mut();
+        unsafe {
+            check(
+                create(ptr::null_mut(), ptr::null_mut(), &mut handle),
+                "deviceCreate",
+            )?;
+        }
+
+        Ok(Self {
+            inner: Arc::new(DeviceHandle {
+                handle,
+                backend: backend.clone(),
+            }),
+        })
+    }
+
+    /// Returns the backend the device belongs to
+    pub fn backend(&self) -> &QnnBackend {
+        &self.inner.backend
+    }
+
+    /// Returns the internal handle to the c object
+    pub(crate) fn handle(&self) -> Qnn_DeviceHandle_t {
+        self.inner.handle
+    }
+}
+
+impl Drop for DeviceHandle {
+    fn drop(&mut self) {
+        let Some(free) = self.backend.library().interface().deviceFree else {
+            return;
+        };
+
+        if let Err(e) = check(unsafe { free(self.handle) }, "deviceFree") {
+            warn!(target: "QnnDevice", "Error cleaning up device: {}", e);
+        }
+    }
+}
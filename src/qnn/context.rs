@@ -0,0 +1,447 @@
+use std::collections::HashMap;
+use std::ffi::{CStr, CString, OsStr};
+use std::ptr;
+use std::sync::Arc;
+
+use log::warn;
+
+use super::backend::{QnnBackend, QnnDevice};
+use super::library::QnnSystemLibrary;
+use super::model::ModelLibrary;
+use super::qnn_bindings::{
+    QnnSystemContext_BinaryInfo_t, QnnSystemContext_Handle_t, Qnn_ContextHandle_t,
+    Qnn_GraphHandle_t, Qnn_Tensor_t,
+};
+use super::tensor::{with_buffer, QnnTensorInfo};
+use super::{check, function};
+use crate::errors::Error;
+use crate::snpe::SnpeTensor;
+
+/// Context restored from a context binary or composed by a model library,
+/// holding the graphs of the model
+pub struct QnnContext {
+    inner: Arc<ContextHandle>,
+}
+
+/// Graph of a context, ready to execute
+pub struct QnnGraph {
+    handle: Qnn_GraphHandle_t,
+    /// Index of the graph's description in the context
+    index: usize,
+    context: Arc<ContextHandle>,
+}
+
+struct ContextHandle {
+    handle: Qnn_ContextHandle_t,
+    graphs: Vec<GraphDescription>,
+    /// Owns the memory the tensor templates of the graphs point into
+    _source: GraphSource,
+    _device: Option<QnnDevice>,
+    backend: QnnBackend,
+}
+
+/// Inputs and outputs of a graph as described by its source. The
+/// templates are copied for every execution with the buffers filled in.
+struct GraphDescription {
+    name: String,
+    /// Handle of a graph composed by a model library. Graphs of a context
+    /// binary are retrieved by name instead.
+    handle: Option<Qnn_GraphHandle_t>,
+    inputs: Vec<(QnnTensorInfo, Qnn_Tensor_t)>,
+    outputs: Vec<(QnnTensorInfo, Qnn_Tensor_t)>,
+}
+
+/// Where the graphs of a context were described
+enum GraphSource {
+    Binary(SystemContext),
+    Library(ModelLibrary),
+}
+
+/// System context used to read the contents of a context binary
+struct SystemContext {
+    handle: QnnSystemContext_Handle_t,
+    library: QnnSystemLibrary,
+}
+
+impl QnnContext {
+    /// Restores a context from a binary produced by qnn-context-binary-generator.
+    /// Backends that manage devices need one to run the context on.
+    pub fn from_binary(
+        backend: &QnnBackend,
+        device: Option<&QnnDevice>,
+        system: &QnnSystemLibrary,
+        binary: &[u8],
+    ) -> Result<Self, Error> {
+        let system = SystemContext::new(system)?;
+        let graphs = unsafe { system.read_graphs(binary)? };
+
+        let create = function(
+            backend.library().interface().contextCreateFromBinary,
+            "contextCreateFromBinary",
+        )?;
+
+        let mut handle = ptr::null_mut();
+        unsafe {
+            check(
+                create(
+                    backend.handle(),
+                    device.map_or(ptr::null_mut(), |device| device.handle()),
+                    ptr::null_mut(),
+                    binary.as_ptr() as *const std::ffi::c_void,
+                    binary.len() as u64,
+                    &mut handle,
+                    ptr::null_mut(),
+                ),
+                "contextCreateFromBinary",
+            )?;
+        }
+
+        Ok(Self {
+            inner: Arc::new(ContextHandle {
+                handle,
+                graphs,
+                _source: GraphSource::Binary(system),
+                _device: device.cloned(),
+                backend: backend.clone(),
+            }),
+        })
+    }
+
+    /// Checks whether the system library can read the graphs of a context
+    /// binary, without restoring it on a backend
+    pub fn is_binary(system: &QnnSystemLibrary, binary: &[u8]) -> bool {
+        SystemContext::new(system)
+            .and_then(|system| unsafe { system.read_graphs(binary) })
+            .is_ok()
+    }
+
+    /// Composes the graphs of a model library built by qnn-model-lib-generator,
+    /// such as libmodel.so, and finalizes them for the backend
+    pub fn from_model_library<P: AsRef<OsStr>>(
+        backend: &QnnBackend,
+        device: Option<&QnnDevice>,
+        path: P,
+    ) -> Result<Self, Error> {
+        let interface = backend.library().interface();
+        let create = function(interface.contextCreate, "contextCreate")?;
+        let finalize = function(interface.graphFinalize, "graphFinalize")?;
+
+        let mut handle = ptr::null_mut();
+        unsafe {
+            check(
+                create(
+                    backend.handle(),
+                    device.map_or(ptr::null_mut(), |device| device.handle()),
+                    ptr::null_mut(),
+                    &mut handle,
+                ),
+                "contextCreate",
+            )?;
+        }
+
+        let library = match unsafe { ModelLibrary::compose(path, backend, handle) } {
+            Ok(library) => library,
+            Err(e) => {
+                free_context(backend, handle);
+                return Err(e);
+            }
+        };
+
+        let graphs = unsafe {
+            library
+                .graphs()
+                .into_iter()
+                .map(|graph| GraphDescription {
+                    name: CStr::from_ptr(graph.graph_name)
+                        .to_string_lossy()
+                        .to_string(),
+                    handle: Some(graph.graph),
+                    inputs: templates(graph.input_tensors, graph.num_input_tensors),
+                    outputs: templates(graph.output_tensors, graph.num_output_tensors),
+                })
+                .collect()
+        };
+
+        // From here on the context frees itself if finalizing fails
+        let inner = Arc::new(ContextHandle {
+            handle,
+            graphs,
+            _source: GraphSource::Library(library),
+            _device: device.cloned(),
+            backend: backend.clone(),
+        });
+
+        for graph in inner.graphs.iter().filter_map(|graph| graph.handle) {
+            unsafe {
+                check(
+                    finalize(graph, ptr::null_mut(), ptr::null_mut()),
+                    "graphFinalize",
+                )?;
+            }
+        }
+
+        Ok(Self { inner })
+    }
+
+    /// Returns the names of the graphs in the context
+    pub fn graph_names(&self) -> Vec<&str> {
+        self.inner
+            .graphs
+            .iter()
+            .map(|graph| graph.name.as_str())
+            .collect()
+    }
+
+    /// Retrieves the graph with the given name
+    pub fn graph(&self, name: &str) -> Result<QnnGraph, Error> {
+        let index = self
+            .inner
+            .graphs
+            .iter()
+            .position(|graph| graph.name == name)
+            .ok_or_else(|| {
+                Error::InvalidArgument(format!(
+                    "Unknown graph {}, the context has {:?}",
+                    name,
+                    self.graph_names()
+                ))
+            })?;
+
+        if let Some(handle) = self.inner.graphs[index].handle {
+            return Ok(QnnGraph {
+                handle,
+                index,
+                context: self.inner.clone(),
+            });
+        }
+
+        let retrieve = function(
+            self.inner.backend.library().interface().graphRetrieve,
+            "graphRetrieve",
+        )?;
+
+        let cname = CString::new(name)?;
+        let mut handle = ptr::null_mut();
+        unsafe {
+            check(
+                retrieve(self.inner.handle, cname.as_ptr(), &mut handle),
+                "graphRetrieve",
+            )?;
+        }
+
+        Ok(QnnGraph {
+            handle,
+            index,
+            context: self.inner.clone(),
+        })
+    }
+}
+
+impl Drop for ContextHandle {
+    fn drop(&mut self) {
+        free_context(&self.backend, self.handle);
+
+        // The graph source, device and backend are freed after the context
+    }
+}
+
+fn free_context(backend: &QnnBackend, handle: Qnn_ContextHandle_t) {
+    let Some(free) = backend.library().interface().contextFree else {
+        return;
+    };
+
+    if let Err(e) = check(unsafe { free(handle, ptr::null_mut()) }, "contextFree") {
+        warn!(target: "QnnContext", "Error cleaning up context: {}", e);
+    }
+}
+
+impl QnnGraph {
+    fn description(&self) -> &GraphDescription {
+        &self.context.graphs[self.index]
+    }
+
+    /// Returns the name of the graph
+    pub fn name(&self) -> &str {
+        &self.description().name
+    }
+
+    /// Returns the descriptions of the inputs, in the order the graph takes them
+    pub fn inputs(&self) -> Vec<&QnnTensorInfo> {
+        self.description()
+            .inputs
+            .iter()
+            .map(|(info, _)| info)
+            .collect()
+    }
+
+    /// Returns the descriptions of the outputs
+    pub fn outputs(&self) -> Vec<&QnnTensorInfo> {
+        self.description()
+            .outputs
+            .iter()
+            .map(|(info, _)| info)
+            .collect()
+    }
+
+    /// Runs the graph on the named input tensors, returning the named outputs.
+    /// Floats are quantized for fixed point inputs and fixed point outputs are
+    /// dequantized to floats.
+    pub fn execute(
+        &self,
+        inputs: &HashMap<String, SnpeTensor>,
+    ) -> Result<HashMap<String, SnpeTensor>, Error> {
+        let description = self.description();
+
+        let mut input_buffers = description
+            .inputs
+            .iter()
+            .map(|(info, _)| {
+                let tensor = inputs.get(&info.name).ok_or_else(|| {
+                    Error::InvalidArgument(format!("Missing input tensor {}", info.name))
+                })?;
+
+                info.encode(tensor)
+            })
+            .collect::<Result<Vec<Vec<u8>>, Error>>()?;
+
+        let mut output_buffers = description
+            .outputs
+            .iter()
+            .map(|(info, _)| Ok(vec![0u8; info.size_bytes()?]))
+            .collect::<Result<Vec<Vec<u8>>, Error>>()?;
+
+        let execute = function(
+            self.context.backend.library().interface().graphExecute,
+            "graphExecute",
+        )?;
+
+        unsafe {
+            let input_tensors: Vec<Qnn_Tensor_t> = description
+                .inputs
+                .iter()
+                .zip(&mut input_buffers)
+                .map(|((_, template), buffer)| with_buffer(template, buffer))
+                .collect();
+            let mut output_tensors: Vec<Qnn_Tensor_t> = description
+                .outputs
+                .iter()
+                .zip(&mut output_buffers)
+                .map(|((_, template), buffer)| with_buffer(template, buffer))
+                .collect();
+
+            check(
+                execute(
+                    self.handle,
+                    input_tensors.as_ptr(),
+                    input_tensors.len() as u32,
+                    output_tensors.as_mut_ptr(),
+                    output_tensors.len() as u32,
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                ),
+                "graphExecute",
+            )?;
+        }
+
+        description
+            .outputs
+            .iter()
+            .zip(&output_buffers)
+            .map(|((info, _), buffer)| Ok((info.name.clone(), info.decode(buffer)?)))
+            .collect()
+    }
+}
+
+impl SystemContext {
+    fn new(library: &QnnSystemLibrary) -> Result<Self, Error> {
+        let create = function(
+            library.interface().systemContextCreate,
+            "systemContextCreate",
+        )?;
+
+        let mut handle = ptr::null_mut();
+        unsafe {
+            check(create(&mut handle), "systemContextCreate")?;
+        }
+
+        Ok(Self {
+            handle,
+            library: library.clone(),
+        })
+    }
+
+    /// Reads the graphs stored in a context binary. The tensor templates point
+    /// into memory owned by the system context.
+    unsafe fn read_graphs(&self, binary: &[u8]) -> Result<Vec<GraphDescription>, Error> {
+        let get_info = function(
+            self.library.interface().systemContextGetBinaryInfo,
+            "systemContextGetBinaryInfo",
+        )?;
+
+        let mut info: *const QnnSystemContext_BinaryInfo_t = ptr::null();
+        let mut info_size = 0;
+        check(
+            get_info(
+                self.handle,
+                binary.as_ptr() as *mut std::ffi::c_void,
+                binary.len() as u64,
+                &mut info,
+                &mut info_size,
+            ),
+            "systemContextGetBinaryInfo",
+        )?;
+
+        if info.is_null() {
+            return Err(Error::UnsupportedFormat(
+                "context binary has no description".to_string(),
+            ));
+        }
+
+        // Later versions of the binary and graph descriptions extend the first
+        let info = &(*info).__bindgen_anon_1.contextBinaryInfoV1;
+        let graphs = slice(info.graphs, info.numGraphs);
+
+        Ok(graphs
+            .iter()
+            .map(|graph| {
+                let graph = &graph.__bindgen_anon_1.graphInfoV1;
+                GraphDescription {
+                    name: CStr::from_ptr(graph.graphName)
+                        .to_string_lossy()
+                        .to_string(),
+                    handle: None,
+                    inputs: templates(graph.graphInputs, graph.numGraphInputs),
+                    outputs: templates(graph.graphOutputs, graph.numGraphOutputs),
+                }
+            })
+            .collect())
+    }
+}
+
+impl Drop for SystemContext {
+    fn drop(&mut self) {
+        let Some(free) = self.library.interface().systemContextFree else {
+            return;
+        };
+
+        if let Err(e) = check(unsafe { free(self.handle) }, "systemContextFree") {
+            warn!(target: "QnnContext", "Error cleaning up system context: {}", e);
+        }
+    }
+}
+
+/// Views a c array, which may be null when empty
+unsafe fn slice<'a, T>(data: *mut T, len: u32) -> &'a [T] {
+    match data.is_null() {
+        true => &[],
+        false => std::slice::from_raw_parts(data, len as usize),
+    }
+}
+
+/// Reads the descriptions of tensors along with copies to use as templates
+unsafe fn templates(tensors: *mut Qnn_Tensor_t, len: u32) -> Vec<(QnnTensorInfo, Qnn_Tensor_t)> {
+    slice(tensors, len)
+        .iter()
+        .map(|tensor| (QnnTensorInfo::from_tensor(tensor), *tensor))
+        .collect()
+}
@@ -0,0 +1,195 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::sync::Arc;
+
+use libloading::{Library, Symbol};
+use semver::Version;
+
+use super::qnn_bindings::{
+    QnnInterface_Implementation_t, QnnInterface_t, QnnSystemInterface_Implementation_t,
+    QnnSystemInterface_t, Qnn_ErrorHandle_t, QNN_API_VERSION_MAJOR, QNN_API_VERSION_MINOR,
+    QNN_API_VERSION_PATCH, QNN_SYSTEM_API_VERSION_MAJOR, QNN_SYSTEM_API_VERSION_MINOR,
+    QNN_SYSTEM_API_VERSION_PATCH,
+};
+use super::{check, to_version};
+use crate::errors::Error;
+
+/// File name of the QNN system library on this platform
+#[cfg(windows)]
+const SYSTEM_LIB_NAME: &str = "QnnSystem.dll";
+#[cfg(not(windows))]
+const SYSTEM_LIB_NAME: &str = "libQnnSystem.so";
+
+type GetProvidersFn =
+    unsafe extern "C" fn(*mut *mut *const QnnInterface_t, *mut u32) -> Qnn_ErrorHandle_t;
+type GetSystemProvidersFn =
+    unsafe extern "C" fn(*mut *mut *const QnnSystemInterface_t, *mut u32) -> Qnn_ErrorHandle_t;
+
+/// QNN backend library, such as libQnnHtp.so, along with the function table
+/// it provides. Clones share the loaded library.
+#[derive(Clone)]
+pub struct QnnLibrary {
+    inner: Arc<LibraryInner<QnnInterface_Implementation_t>>,
+}
+
+/// QNN system library, used to read the graphs stored in context binaries
+#[derive(Clone)]
+pub struct QnnSystemLibrary {
+    inner: Arc<LibraryInner<QnnSystemInterface_Implementation_t>>,
+}
+
+struct LibraryInner<T> {
+    path: PathBuf,
+    version: Version,
+    interface: T,
+    /// Keeps the functions in the table loaded
+    _library: Library,
+}
+
+impl QnnLibrary {
+    /// Loads a backend library, given either a path or a file name on the
+    /// library search path, and picks the interface provider matching the
+    /// headers the bindings were generated from
+    pub fn open<P: AsRef<OsStr>>(path: P) -> Result<Self, Error> {
+        let expected = Version::new(
+            QNN_API_VERSION_MAJOR as u64,
+            QNN_API_VERSION_MINOR as u64,
+            QNN_API_VERSION_PATCH as u64,
+        );
+
+        unsafe {
+            let library = Library::new(path.as_ref())?;
+            let get_providers: Symbol<GetProvidersFn> =
+                library.get(b"QnnInterface_getProviders\0")?;
+
+            let mut providers = ptr::null_mut();
+            let mut count = 0;
+            check(
+                get_providers(&mut providers, &mut count),
+                "QnnInterface_getProviders",
+            )?;
+
+            let provider = &*select(&expected, providers, count, |provider| {
+                to_version(&provider.apiVersion.coreApiVersion)
+            })?;
+
+            // The table is the only member of a union named after the api version
+            let interface =
+                *(&provider.__bindgen_anon_1 as *const _ as *const QnnInterface_Implementation_t);
+
+            Ok(Self {
+                inner: Arc::new(LibraryInner {
+                    path: PathBuf::from(path.as_ref()),
+                    version: to_version(&provider.apiVersion.coreApiVersion),
+                    interface,
+                    _library: library,
+                }),
+            })
+        }
+    }
+
+    /// Returns the path the library was loaded from
+    pub fn path(&self) -> &Path {
+        &self.inner.path
+    }
+
+    /// Returns the core api version of the selected provider
+    pub fn version(&self) -> &Version {
+        &self.inner.version
+    }
+
+    /// Returns the function table of the backend
+    pub(crate) fn interface(&self) -> &QnnInterface_Implementation_t {
+        &self.inner.interface
+    }
+}
+
+impl QnnSystemLibrary {
+    /// Loads the system library from the library search path
+    pub fn from_env() -> Result<Self, Error> {
+        Self::open(SYSTEM_LIB_NAME)
+    }
+
+    /// Loads the system library, given either a path or a file name on the
+    /// library search path
+    pub fn open<P: AsRef<OsStr>>(path: P) -> Result<Self, Error> {
+        let expected = Version::new(
+            QNN_SYSTEM_API_VERSION_MAJOR as u64,
+            QNN_SYSTEM_API_VERSION_MINOR as u64,
+            QNN_SYSTEM_API_VERSION_PATCH as u64,
+        );
+
+        unsafe {
+            let library = Library::new(path.as_ref())?;
+            let get_providers: Symbol<GetSystemProvidersFn> =
+                library.get(b"QnnSystemInterface_getProviders\0")?;
+
+            let mut providers = ptr::null_mut();
+            let mut count = 0;
+            check(
+                get_providers(&mut providers, &mut count),
+                "QnnSystemInterface_getProviders",
+            )?;
+
+            let provider = &*select(&expected, providers, count, |provider| {
+                to_version(&provider.systemApiVersion)
+            })?;
+
+            let interface = *(&provider.__bindgen_anon_1 as *const _
+                as *const QnnSystemInterface_Implementation_t);
+
+            Ok(Self {
+                inner: Arc::new(LibraryInner {
+                    path: PathBuf::from(path.as_ref()),
+                    version: to_version(&provider.systemApiVersion),
+                    interface,
+                    _library: library,
+                }),
+            })
+        }
+    }
+
+    /// Returns the path the library was loaded from
+    pub fn path(&self) -> &Path {
+        &self.inner.path
+    }
+
+    /// Returns the system api version of the selected provider
+    pub fn version(&self) -> &Version {
+        &self.inner.version
+    }
+
+    /// Returns the function table of the system library
+    pub(crate) fn interface(&self) -> &QnnSystemInterface_Implementation_t {
+        &self.inner.interface
+    }
+}
+
+/// Picks the first provider whose table has everything the bindings expect,
+/// which is any provider with the same major and at least the same minor version
+unsafe fn select<T>(
+    expected: &Version,
+    providers: *mut *const T,
+    count: u32,
+    version: impl Fn(&T) -> Version,
+) -> Result<*const T, Error> {
+    if providers.is_null() || count == 0 {
+        return Err(Error::InvalidState(
+            "QNN library has no interface providers".to_string(),
+        ));
+    }
+
+    let providers = std::slice::from_raw_parts(providers, count as usize);
+    for &provider in providers {
+        let found = version(&*provider);
+        if found.major == expected.major && found.minor >= expected.minor {
+            return Ok(provider);
+        }
+    }
+
+    Err(Error::IncompatibleVersion {
+        expected: expected.clone(),
+        found: version(&*providers[0]),
+    })
+}
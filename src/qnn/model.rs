@@ -0,0 +1,124 @@
+use std::ffi::{c_void, OsStr};
+use std::os::raw::c_char;
+use std::ptr;
+
+use libloading::{Library, Symbol};
+use log::warn;
+
+use super::backend::QnnBackend;
+use super::qnn_bindings::{
+    QnnInterface_Implementation_t, Qnn_BackendHandle_t, Qnn_ContextHandle_t, Qnn_GraphHandle_t,
+    Qnn_Tensor_t,
+};
+use crate::errors::{Error, ErrorDetails};
+
+/// Graph composed by a model library, laid out like GraphInfo_t in the
+/// sdk's GraphInfo.h. Model libraries aren't part of the QNN api, so the
+/// headers don't declare it.
+#[repr(C)]
+pub(crate) struct GraphInfo {
+    pub graph: Qnn_GraphHandle_t,
+    pub graph_name: *mut c_char,
+    pub input_tensors: *mut Qnn_Tensor_t,
+    pub num_input_tensors: u32,
+    pub output_tensors: *mut Qnn_Tensor_t,
+    pub num_output_tensors: u32,
+}
+
+type ComposeGraphsFn = unsafe extern "C" fn(
+    backend: Qnn_BackendHandle_t,
+    interface: QnnInterface_Implementation_t,
+    context: Qnn_ContextHandle_t,
+    graph_configs: *const *const c_void,
+    num_graph_configs: u32,
+    graphs: *mut *mut *mut GraphInfo,
+    num_graphs: *mut u32,
+    debug: bool,
+    log_callback: *const c_void,
+    max_log_level: u32,
+) -> u32;
+type FreeGraphsInfoFn =
+    unsafe extern "C" fn(graphs: *mut *mut *mut GraphInfo, num_graphs: u32) -> u32;
+
+/// Model library built by qnn-model-lib-generator, along with the graphs it
+/// composed. The graph descriptions stay valid until it's dropped.
+pub(crate) struct ModelLibrary {
+    graphs: *mut *mut GraphInfo,
+    num_graphs: u32,
+    free_graphs: FreeGraphsInfoFn,
+    /// Keeps the code behind the graphs loaded
+    _library: Library,
+}
+
+impl ModelLibrary {
+    /// Loads a model library and composes its graphs into the context
+    pub(crate) unsafe fn compose<P: AsRef<OsStr>>(
+        path: P,
+        backend: &QnnBackend,
+        context: Qnn_ContextHandle_t,
+    ) -> Result<Self, Error> {
+        let library = Library::new(path.as_ref())?;
+        let compose: Symbol<ComposeGraphsFn> = library.get(b"QnnModel_composeGraphs\0")?;
+        let free_graphs = *library.get::<FreeGraphsInfoFn>(b"QnnModel_freeGraphsInfo\0")?;
+
+        let mut graphs = ptr::null_mut();
+        let mut num_graphs = 0;
+        model_check(
+            compose(
+                backend.handle(),
+                *backend.library().interface(),
+                context,
+                ptr::null(),
+                0,
+                &mut graphs,
+                &mut num_graphs,
+                false,
+                ptr::null(),
+                0,
+            ),
+            "QnnModel_composeGraphs",
+        )?;
+
+        Ok(Self {
+            graphs,
+            num_graphs,
+            free_graphs,
+            _library: library,
+        })
+    }
+
+    /// Returns the graphs composed by the library
+    pub(crate) unsafe fn graphs(&self) -> Vec<&GraphInfo> {
+        if self.graphs.is_null() {
+            return vec![];
+        }
+
+        std::slice::from_raw_parts(self.graphs, self.num_graphs as usize)
+            .iter()
+            .map(|&graph| &*graph)
+            .collect()
+    }
+}
+
+impl Drop for ModelLibrary {
+    fn drop(&mut self) {
+        let result = unsafe { (self.free_graphs)(&mut self.graphs, self.num_graphs) };
+        if let Err(e) = model_check(result, "QnnModel_freeGraphsInfo") {
+            warn!(target: "QnnContext", "Error cleaning up model library: {}", e);
+        }
+    }
+}
+
+/// Turns the ModelError_t returned by a model library into an error naming
+/// the call
+fn model_check(result: u32, call: &str) -> Result<(), Error> {
+    if result == 0 {
+        return Ok(());
+    }
+
+    Err(Error::Qnn(ErrorDetails {
+        code: result,
+        message: format!("{} failed", call),
+        info: String::new(),
+    }))
+}
@@ -0,0 +1,351 @@
+use std::ffi::CStr;
+
+use super::qnn_bindings::*;
+use crate::errors::Error;
+use crate::snpe::{SnpeTensor, TensorData};
+
+/// Data type of the elements of a QNN tensor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QnnDataType {
+    Float32,
+    Float16,
+    Uint8,
+    Uint16,
+    Int32,
+    /// 8 bit fixed point, see QnnTensorInfo::quantization
+    UFixedPoint8,
+    /// 16 bit fixed point, see QnnTensorInfo::quantization
+    UFixedPoint16,
+    /// Any other Qnn_DataType_t, which can't be passed in or out
+    Other(u32),
+}
+
+impl QnnDataType {
+    /// Converts the QNN data type id
+    fn from_id(id: Qnn_DataType_t) -> Self {
+        match id {
+            Qnn_DataType_t_QNN_DATATYPE_FLOAT_32 => QnnDataType::Float32,
+            Qnn_DataType_t_QNN_DATATYPE_FLOAT_16 => QnnDataType::Float16,
+            Qnn_DataType_t_QNN_DATATYPE_UINT_8 => QnnDataType::Uint8,
+            Qnn_DataType_t_QNN_DATATYPE_UINT_16 => QnnDataType::Uint16,
+            Qnn_DataType_t_QNN_DATATYPE_INT_32 => QnnDataType::Int32,
+            Qnn_DataType_t_QNN_DATATYPE_UFIXED_POINT_8 => QnnDataType::UFixedPoint8,
+            Qnn_DataType_t_QNN_DATATYPE_UFIXED_POINT_16 => QnnDataType::UFixedPoint16,
+            other => QnnDataType::Other(other as u32),
+        }
+    }
+
+    /// Returns the size in bytes of a single element
+    pub fn element_size(&self) -> Option<usize> {
+        match self {
+            QnnDataType::Uint8 | QnnDataType::UFixedPoint8 => Some(1),
+            QnnDataType::Float16 | QnnDataType::Uint16 | QnnDataType::UFixedPoint16 => Some(2),
+            QnnDataType::Float32 | QnnDataType::Int32 => Some(4),
+            QnnDataType::Other(_) => None,
+        }
+    }
+}
+
+/// Scale and offset of a fixed point tensor, where a quantized value q
+/// stands for scale * (q + offset)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QnnScaleOffset {
+    pub scale: f32,
+    pub offset: i32,
+}
+
+/// Description of a graph input or output stored in a context binary
+#[derive(Debug, Clone, PartialEq)]
+pub struct QnnTensorInfo {
+    pub name: String,
+    /// Dimensions of the tensor
+    pub shape: Vec<usize>,
+    pub data_type: QnnDataType,
+    /// Quantization parameters, only present for fixed point tensors
+    pub quantization: Option<QnnScaleOffset>,
+}
+
+impl QnnTensorInfo {
+    /// Reads the description of a tensor. Later tensor versions extend the
+    /// first, so every version is read through it.
+    pub(crate) unsafe fn from_tensor(tensor: &Qnn_Tensor_t) -> Self {
+        let v1 = &tensor.__bindgen_anon_1.v1;
+
+        let dims: &[u32] = match v1.dimensions.is_null() {
+            true => &[],
+            false => std::slice::from_raw_parts(v1.dimensions, v1.rank as usize),
+        };
+
+        let params = &v1.quantizeParams;
+        let quantization = (params.encodingDefinition == Qnn_Definition_t_QNN_DEFINITION_DEFINED
+            && params.quantizationEncoding
+                == Qnn_QuantizationEncoding_t_QNN_QUANTIZATION_ENCODING_SCALE_OFFSET)
+            .then(|| {
+                let encoding = params.__bindgen_anon_1.scaleOffsetEncoding;
+                QnnScaleOffset {
+                    scale: encoding.scale,
+                    offset: encoding.offset,
+                }
+            });
+
+        Self {
+            name: CStr::from_ptr(v1.name).to_string_lossy().to_string(),
+            shape: dims.iter().map(|&d| d as usize).collect(),
+            data_type: QnnDataType::from_id(v1.dataType),
+            quantization,
+        }
+    }
+
+    /// Returns the number of elements in the tensor
+    pub fn num_elements(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    /// Encodes a tensor as the raw buffer QNN expects for this input, quantizing
+    /// floats for fixed point inputs
+    pub(crate) fn encode(&self, tensor: &SnpeTensor) -> Result<Vec<u8>, Error> {
+        if tensor.num_elements() != self.num_elements() {
+            return Err(Error::ShapeMismatch(format!(
+                "Input {} expects shape {:?} but got {:?}",
+                self.name,
+                self.shape,
+                tensor.shape()
+            )));
+        }
+
+        let scale_offset = self.quantization.unwrap_or(QnnScaleOffset {
+            scale: 1.0,
+            offset: 0,
+        });
+
+        let bytes = match (self.data_type, tensor.data()) {
+            (QnnDataType::Float32, _) => tensor
+                .to_f32()
+                .iter()
+                .flat_map(|v| v.to_ne_bytes())
+                .collect(),
+            (QnnDataType::Float16, _) => tensor
+                .to_f32()
+                .iter()
+                .flat_map(|&v| f32_to_f16(v).to_ne_bytes())
+                .collect(),
+            (QnnDataType::Uint8 | QnnDataType::UFixedPoint8, TensorData::Uint8(values)) => {
+                values.clone()
+            }
+            (QnnDataType::Uint16 | QnnDataType::UFixedPoint16, TensorData::Uint16(values)) => {
+                values.iter().flat_map(|v| v.to_ne_bytes()).collect()
+            }
+            (QnnDataType::Int32, TensorData::Int32(values)) => {
+                values.iter().flat_map(|v| v.to_ne_bytes()).collect()
+            }
+            (QnnDataType::UFixedPoint8, TensorData::Float(values)) => values
+                .iter()
+                .map(|&v| quantize(v, scale_offset, u8::MAX as f32) as u8)
+                .collect(),
+            (QnnDataType::UFixedPoint16, TensorData::Float(values)) => values
+                .iter()
+                .flat_map(|&v| (quantize(v, scale_offset, u16::MAX as f32) as u16).to_ne_bytes())
+                .collect(),
+            (data_type, _) => {
+                return Err(Error::UnsupportedElementType(format!(
+                    "{:?} data for {:?} input {}",
+                    tensor.element_type(),
+                    data_type,
+                    self.name
+                )))
+            }
+        };
+
+        Ok(bytes)
+    }
+
+    /// Decodes a raw output buffer, dequantizing fixed point outputs to floats
+    pub(crate) fn decode(&self, bytes: &[u8]) -> Result<SnpeTensor, Error> {
+        let scale_offset = self.quantization.unwrap_or(QnnScaleOffset {
+            scale: 1.0,
+            offset: 0,
+        });
+        let dequantize = |q: f32| scale_offset.scale * (q + scale_offset.offset as f32);
+
+        let data = match self.data_type {
+            QnnDataType::Float32 => TensorData::Float(
+                bytes
+                    .chunks_exact(4)
+                    .map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+                    .collect(),
+            ),
+            QnnDataType::Float16 => TensorData::Float(
+                bytes
+                    .chunks_exact(2)
+                    .map(|b| f16_to_f32(u16::from_ne_bytes([b[0], b[1]])))
+                    .collect(),
+            ),
+            QnnDataType::Uint8 => TensorData::Uint8(bytes.to_vec()),
+            QnnDataType::Uint16 => TensorData::Uint16(
+                bytes
+                    .chunks_exact(2)
+                    .map(|b| u16::from_ne_bytes([b[0], b[1]]))
+                    .collect(),
+            ),
+            QnnDataType::Int32 => TensorData::Int32(
+                bytes
+                    .chunks_exact(4)
+                    .map(|b| i32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+                    .collect(),
+            ),
+            QnnDataType::UFixedPoint8 => {
+                TensorData::Float(bytes.iter().map(|&q| dequantize(q as f32)).collect())
+            }
+            QnnDataType::UFixedPoint16 => TensorData::Float(
+                bytes
+                    .chunks_exact(2)
+                    .map(|b| dequantize(u16::from_ne_bytes([b[0], b[1]]) as f32))
+                    .collect(),
+            ),
+            QnnDataType::Other(id) => {
+                return Err(Error::UnsupportedElementType(format!(
+                    "QNN data type {:#x} of output {}",
+                    id, self.name
+                )))
+            }
+        };
+
+        SnpeTensor::new(&self.shape, data)
+    }
+
+    /// Returns the size in bytes of the tensor's buffer
+    pub(crate) fn size_bytes(&self) -> Result<usize, Error> {
+        let element_size = self.data_type.element_size().ok_or_else(|| {
+            Error::UnsupportedElementType(format!("{:?} tensor {}", self.data_type, self.name))
+        })?;
+
+        Ok(self.num_elements() * element_size)
+    }
+}
+
+/// Copies a tensor description from a context binary, pointing it at a
+/// client buffer
+pub(crate) unsafe fn with_buffer(template: &Qnn_Tensor_t, buffer: &mut [u8]) -> Qnn_Tensor_t {
+    let mut tensor = *template;
+    let v1 = &mut tensor.__bindgen_anon_1.v1;
+
+    v1.memType = Qnn_TensorMemType_t_QNN_TENSORMEMTYPE_RAW;
+    v1.__bindgen_anon_1.clientBuf = Qnn_ClientBuffer_t {
+        data: buffer.as_mut_ptr() as *mut std::ffi::c_void,
+        dataSize: buffer.len() as u32,
+    };
+
+    tensor
+}
+
+/// Quantizes a float, clamping it to the range of the fixed point type
+fn quantize(value: f32, scale_offset: QnnScaleOffset, max: f32) -> f32 {
+    ((value / scale_offset.scale).round() - scale_offset.offset as f32).clamp(0.0, max)
+}
+
+/// Converts a float to the bits of the nearest half precision float
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    // Infinity and nan keep a nonzero mantissa for nan
+    if exponent == 0xff {
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    // Too small for a normal half, so shift the mantissa into a subnormal one
+    if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        let half = (mantissa >> shift) as u16;
+        let round = ((mantissa >> (shift - 1)) & 1) as u16;
+        return sign | (half + round);
+    }
+
+    // A carry out of the mantissa correctly bumps the exponent
+    let half = sign | ((exponent as u16) << 10) | (mantissa >> 13) as u16;
+    half + ((mantissa >> 12) & 1) as u16
+}
+
+/// Converts the bits of a half precision float to a float
+fn f16_to_f32(half: u16) -> f32 {
+    let sign = ((half & 0x8000) as u32) << 16;
+    let exponent = ((half >> 10) & 0x1f) as u32;
+    let mantissa = (half & 0x3ff) as u32;
+
+    let bits = match exponent {
+        0 if mantissa == 0 => sign,
+        0 => {
+            let value = mantissa as f32 * 2f32.powi(-24);
+            return if sign != 0 { -value } else { value };
+        }
+        0x1f => sign | 0x7f80_0000 | (mantissa << 13),
+        _ => sign | ((exponent + 112) << 23) | (mantissa << 13),
+    };
+
+    f32::from_bits(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{f16_to_f32, f32_to_f16, QnnDataType, QnnScaleOffset, QnnTensorInfo};
+    use crate::snpe::{SnpeTensor, TensorData};
+
+    #[test]
+    fn half_floats() {
+        for value in [0.0, 1.0, -2.5, 65504.0, 6.1035156e-5, 5.9604645e-8] {
+            assert_eq!(f16_to_f32(f32_to_f16(value)), value);
+        }
+
+        assert_eq!(f32_to_f16(1.0), 0x3c00);
+        assert_eq!(f32_to_f16(1e6), 0x7c00);
+        assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+    }
+
+    #[test]
+    fn fixed_point_round_trip() {
+        let info = QnnTensorInfo {
+            name: "input".to_string(),
+            shape: vec![4],
+            data_type: QnnDataType::UFixedPoint8,
+            quantization: Some(QnnScaleOffset {
+                scale: 0.5,
+                offset: -10,
+            }),
+        };
+
+        let tensor = SnpeTensor::new(&[4], TensorData::Float(vec![-5.0, 0.0, 1.0, 500.0])).unwrap();
+        let bytes = info.encode(&tensor).unwrap();
+        assert_eq!(bytes, vec![0, 10, 12, 255]);
+
+        let decoded = info.decode(&bytes).unwrap();
+        assert_eq!(
+            decoded.data(),
+            &TensorData::Float(vec![-5.0, 0.0, 1.0, 122.5])
+        );
+    }
+
+    #[test]
+    fn encode_checks_shape() {
+        let info = QnnTensorInfo {
+            name: "input".to_string(),
+            shape: vec![2, 2],
+            data_type: QnnDataType::Float32,
+            quantization: None,
+        };
+
+        let tensor = SnpeTensor::new(&[3], TensorData::Float(vec![0.0; 3])).unwrap();
+        assert!(info.encode(&tensor).is_err());
+    }
+}
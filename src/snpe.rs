@@ -1,49 +1,181 @@
+mod builder;
+mod library;
+mod runtime;
+mod tensor;
+mod tensor_info;
+mod user_buffer;
+use std::collections::HashMap;
 use std::ffi::{CStr, CString};
 use std::path::PathBuf;
 
 use libloading::Library;
+use log::warn;
 use semver::{BuildMetadata, Prerelease, Version};
-use tensor_rs::tensor::Tensor;
+
+pub(crate) use self::builder::{check, string_list};
+pub use self::builder::{PerformanceProfile, SnpeBuilder};
+pub use self::library::{SnpeLibrary, VersionPolicy};
+pub use self::runtime::{Runtime, RuntimeList};
+use self::tensor::TensorMap;
+pub use self::tensor::{AsSnpeTensor, FromSnpeTensor, SnpeTensor, TensorData};
+pub(crate) use self::tensor_info::read_shape;
+pub use self::tensor_info::{ElementType, Quantization, TensorInfo};
+pub use self::user_buffer::{packed_strides, UserBufferElement, UserBufferEncoding, UserBufferMap};
+use crate::dlcontainer::DlContainer;
+use crate::errors::Error;
 
 pub mod snpe_bindings {
     include!(concat!(env!("OUT_DIR"), "/snpe_bindings.rs"));
 
+    use std::ffi::OsStr;
+    use std::path::{Path, PathBuf};
+    use std::sync::OnceLock;
+
+    use crate::errors::Error;
+
     pub static LIB: &str = concat!(env!("SNPE_LIB_DIR"), "/libSNPE.so");
 
-    pub unsafe fn get() -> SNPE {
-        SNPE::new(LIB).unwrap()
+    /// Library shared by the whole process along with the file it came from
+    static INSTANCE: OnceLock<(PathBuf, SNPE)> = OnceLock::new();
+
+    /// Returns the loaded library, searching the environment for it if
+    /// nothing has been loaded yet
+    pub unsafe fn get() -> Result<&'static SNPE, Error> {
+        match INSTANCE.get() {
+            Some((_, snpe)) => Ok(snpe),
+            None => {
+                super::SnpeLibrary::from_env()?;
+                Ok(loaded())
+            }
+        }
+    }
+
+    /// Opens the library at the given path. Fails if a library is already
+    /// loaded, since every existing handle belongs to that one.
+    pub unsafe fn load<P: AsRef<OsStr>>(path: P) -> Result<&'static SNPE, Error> {
+        if INSTANCE.get().is_some() {
+            return Err(Error::InvalidState(
+                "SNPE library is already loaded".to_string(),
+            ));
+        }
+
+        let snpe = SNPE::new(path.as_ref())?;
+        super::library::check_version(&super::read_version(&snpe))?;
+
+        // Another thread may have won the race, in which case ours is dropped
+        let _ = INSTANCE.set((PathBuf::from(path.as_ref()), snpe));
+        Ok(loaded())
+    }
+
+    /// Returns the library for objects that hold a handle, which can only
+    /// have been created once the library was loaded
+    pub(crate) unsafe fn loaded() -> &'static SNPE {
+        &INSTANCE
+            .get()
+            .expect("SNPE library must be loaded while handles are alive")
+            .1
+    }
+
+    /// Returns the file the library was loaded from, if it's been loaded
+    pub(crate) fn loaded_path() -> Option<&'static Path> {
+        INSTANCE.get().map(|(path, _)| path.as_path())
     }
 }
 
 /// Instance of the SNPE runtime
-struct Snpe {
+pub struct Snpe {
     handle: snpe_bindings::Snpe_SNPE_Handle_t,
 }
 
 impl Snpe {
-    /// Creates a new SNPE instance
-    fn new() -> Self {
-        // todo: implement
+    /// Returns a builder to create a new SNPE instance from the container
+    pub fn builder(container: &DlContainer) -> SnpeBuilder<'_> {
+        SnpeBuilder::new(container)
+    }
+
+    /// Runs the network on the named input tensors, returning the named
+    /// outputs. Any tensor type implementing AsSnpeTensor can be passed in
+    /// and the outputs can be any type implementing FromSnpeTensor.
+    pub fn execute<I, O>(&self, inputs: &HashMap<String, I>) -> Result<HashMap<String, O>, Error>
+    where
+        I: AsSnpeTensor,
+        O: FromSnpeTensor,
+    {
+        let mut input_map = TensorMap::new()?;
+        for (name, tensor) in inputs {
+            input_map.insert(name, tensor)?;
+        }
+
+        let output_map = TensorMap::new()?;
 
-        Self {
-            handle: std::ptr::null_mut(),
+        unsafe {
+            let snpe = snpe_bindings::loaded();
+            let code = snpe.Snpe_SNPE_ExecuteITensors(
+                self.handle,
+                input_map.handle(),
+                output_map.handle(),
+            );
+
+            if code != 0 {
+                return Err(Error::last(&snpe));
+            }
         }
+
+        output_map
+            .to_tensors()?
+            .into_iter()
+            .map(|(name, tensor)| Ok((name, O::from_snpe_tensor(tensor)?)))
+            .collect()
     }
 
-    // fn get_input_tensors(&self) -> Result<Vec<TensorInfo>, &str> {
-    //     let snpe = unsafe { snpe_bindings::SNPE::new(snpe_bindings::LIB).unwrap() };
+    /// Runs the network on SnpeTensors, returning the named outputs. Inputs
+    /// of any element type are converted to floats.
+    pub fn execute_tensors(
+        &self,
+        inputs: &HashMap<String, SnpeTensor>,
+    ) -> Result<HashMap<String, SnpeTensor>, Error> {
+        self.execute(inputs)
+    }
 
-    //     let cnames = self.get_input_tensor_cnames()?;
-    //     let friendly_names = self.get_input_tensor_names()?;
-    //     let result: Vec<TensorInfo> = vec![];
+    /// Runs the network directly on the registered buffers, writing the
+    /// results into the output buffers. The network must have been built with
+    /// user supplied buffers enabled.
+    pub fn execute_user_buffers(
+        &self,
+        inputs: &UserBufferMap,
+        outputs: &mut UserBufferMap,
+    ) -> Result<(), Error> {
+        unsafe {
+            let snpe = snpe_bindings::loaded();
+            let code =
+                snpe.Snpe_SNPE_ExecuteUserBuffers(self.handle, inputs.handle(), outputs.handle());
 
-    //     for i in 0..cnames.len() {
-    //         snpe.Snpe_GetIn
-    //     }
-    // }
+            if code != 0 {
+                return Err(Error::last(&snpe));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the descriptions of the input tensors of the network
+    pub fn input_tensors(&self) -> Result<Vec<TensorInfo>, Error> {
+        self.get_input_tensor_cnames()?
+            .iter()
+            .map(|name| TensorInfo::from_network(self.handle, name))
+            .collect()
+    }
+
+    /// Returns the descriptions of the output tensors of the network
+    pub fn output_tensors(&self) -> Result<Vec<TensorInfo>, Error> {
+        self.get_output_tensor_cnames()?
+            .iter()
+            .map(|name| TensorInfo::from_network(self.handle, name))
+            .collect()
+    }
 
     /// Returns the list of names of input tensors to the network
-    fn get_input_tensor_names(&self) -> Result<Vec<String>, &str> {
+    pub fn get_input_tensor_names(&self) -> Result<Vec<String>, Error> {
         let names = self
             .get_input_tensor_cnames()?
             .into_iter()
@@ -54,14 +186,14 @@ impl Snpe {
     }
 
     /// Returns a list of owned input tensor names
-    fn get_input_tensor_cnames(&self) -> Result<Vec<CString>, &str> {
+    fn get_input_tensor_cnames(&self) -> Result<Vec<CString>, Error> {
         let mut result: Vec<CString> = vec![];
 
-        let snpe = unsafe { snpe_bindings::get() };
+        let snpe = unsafe { snpe_bindings::loaded() };
         let inputNamesHandle = unsafe { snpe.Snpe_SNPE_GetInputTensorNames(self.handle) };
 
         if inputNamesHandle.is_null() {
-            return Err("Failed to get input tensor names");
+            return Err(unsafe { Error::last(&snpe) });
         }
 
         let n = unsafe { snpe.Snpe_StringList_Size(inputNamesHandle) };
@@ -79,51 +211,102 @@ impl Snpe {
 
         Ok(result)
     }
+
+    /// Returns the list of names of output tensors of the network
+    pub fn get_output_tensor_names(&self) -> Result<Vec<String>, Error> {
+        let names = self
+            .get_output_tensor_cnames()?
+            .into_iter()
+            .map(|c| c.to_string_lossy().to_string())
+            .collect();
+
+        Ok(names)
+    }
+
+    /// Returns a list of owned output tensor names
+    fn get_output_tensor_cnames(&self) -> Result<Vec<CString>, Error> {
+        let mut result: Vec<CString> = vec![];
+
+        let snpe = unsafe { snpe_bindings::loaded() };
+        let outputNamesHandle = unsafe { snpe.Snpe_SNPE_GetOutputTensorNames(self.handle) };
+
+        if outputNamesHandle.is_null() {
+            return Err(unsafe { Error::last(&snpe) });
+        }
+
+        let n = unsafe { snpe.Snpe_StringList_Size(outputNamesHandle) };
+        for i in 0..n {
+            let cstr = unsafe { CStr::from_ptr(snpe.Snpe_StringList_At(outputNamesHandle, i)) };
+            result.push(cstr.to_owned());
+        }
+
+        unsafe {
+            snpe.Snpe_StringList_Delete(outputNamesHandle);
+        }
+
+        Ok(result)
+    }
+}
+
+impl Drop for Snpe {
+    fn drop(&mut self) {
+        unsafe {
+            let snpe = snpe_bindings::loaded();
+            let errorCode = snpe.Snpe_SNPE_Delete(self.handle);
+
+            if errorCode != 0 {
+                let error = Error::last(&snpe);
+                warn!(target: "Snpe", "Error cleaning up network: {}", error);
+            }
+        }
+    }
 }
 
 /// Returns the SNPE library version
-fn get_version() -> Version {
-    let version: Version;
+pub fn get_version() -> Result<Version, Error> {
     unsafe {
-        let snpe = snpe_bindings::get();
-
-        let versionHandle = snpe.Snpe_Util_GetLibraryVersion();
+        let snpe = snpe_bindings::get()?;
+        Ok(read_version(snpe))
+    }
+}
 
-        version = Version {
-            major: snpe.Snpe_DlVersion_GetMajor(versionHandle) as u64,
-            minor: snpe.Snpe_DlVersion_GetMinor(versionHandle) as u64,
-            patch: snpe.Snpe_DlVersion_GetTeeny(versionHandle) as u64,
-            pre: Prerelease::EMPTY,
-            build: BuildMetadata::EMPTY,
-        };
+/// Reads the version of a library, which may not be the loaded one yet
+unsafe fn read_version(snpe: &snpe_bindings::SNPE) -> Version {
+    let versionHandle = snpe.Snpe_Util_GetLibraryVersion();
 
-        snpe.Snpe_DlVersion_Delete(versionHandle);
-    }
+    let version = Version {
+        major: snpe.Snpe_DlVersion_GetMajor(versionHandle) as u64,
+        minor: snpe.Snpe_DlVersion_GetMinor(versionHandle) as u64,
+        patch: snpe.Snpe_DlVersion_GetTeeny(versionHandle) as u64,
+        pre: Prerelease::EMPTY,
+        build: BuildMetadata::EMPTY,
+    };
 
+    snpe.Snpe_DlVersion_Delete(versionHandle);
     version
 }
 
 /// Returns the list of available accelerator devices
-fn get_available_devices() -> Vec<Device> {
+pub fn get_available_devices() -> Vec<Device> {
     let devices = [Device::Cpu, Device::Gpu, Device::Npu, Device::Aip];
-    let mut available: Vec<Device> = vec![];
-
-    unsafe {
-        let snpe = snpe_bindings::get();
 
-        for device in devices {
-            if snpe.Snpe_Util_IsRuntimeAvailable(device.id()) != 0 {
-                available.push(device);
-            }
-        }
-    }
+    devices
+        .into_iter()
+        .filter(|device| device.is_available())
+        .collect()
+}
 
-    available
+/// Returns every runtime available on this machine
+pub fn get_available_runtimes() -> Vec<Runtime> {
+    Runtime::ALL
+        .into_iter()
+        .filter(|runtime| runtime.is_available())
+        .collect()
 }
 
 /// Enum containing the possible runtime environments for the SNPE library
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-enum Device {
+pub enum Device {
     /// Standard Cpu device with float32 math
     Cpu,
     /// Adreno Gpu device with 16 bit data and 32 bit math
@@ -136,7 +319,7 @@ enum Device {
 
 impl Device {
     /// Returns a friendly name for the device
-    fn name(&self) -> String {
+    pub fn name(&self) -> String {
         match self {
             Device::Cpu => "CPU",
             Device::Gpu => "GPU",
@@ -146,40 +329,43 @@ impl Device {
         .to_string()
     }
 
-    /// Returns the SNPE runtime id
-    fn id(&self) -> i32 {
+    /// Returns the default runtime of the device
+    pub fn runtime(&self) -> Runtime {
         match self {
-            Device::Cpu => snpe_bindings::Snpe_Runtime_t_SNPE_RUNTIME_CPU_FLOAT32,
-            Device::Gpu => snpe_bindings::Snpe_Runtime_t_SNPE_RUNTIME_GPU_FLOAT32_16_HYBRID,
-            Device::Npu => snpe_bindings::Snpe_Runtime_t_SNPE_RUNTIME_DSP_FIXED8_TF,
-            Device::Aip => snpe_bindings::Snpe_Runtime_t_SNPE_RUNTIME_AIP_FIXED_TF,
+            Device::Cpu => Runtime::CPU,
+            Device::Gpu => Runtime::GPU,
+            Device::Npu => Runtime::DSP,
+            Device::Aip => Runtime::AIP,
         }
     }
 
-    /// Returns if the device is available
-    fn is_available(&self) -> bool {
-        unsafe {
-            let snpe = snpe_bindings::get();
-            snpe.Snpe_Util_IsRuntimeAvailable(self.id()) != 0
-        }
+    /// Returns the SNPE runtime id
+    pub(crate) fn id(&self) -> i32 {
+        self.runtime().id()
     }
-}
 
-struct TensorInfo {
-    name: String,
-    shape: Vec<u64>,
+    /// Returns if the device is available
+    pub fn is_available(&self) -> bool {
+        self.runtime().is_available()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use semver::Version;
+    use std::collections::HashMap;
 
-    use crate::snpe::{get_available_devices, get_version, Device, Snpe};
+    use tensor_rs::tensor::Tensor;
+
+    use crate::dlcontainer::DlContainer;
+    use crate::snpe::{
+        get_available_devices, get_version, packed_strides, Device, ElementType,
+        PerformanceProfile, Snpe, SnpeLibrary, UserBufferEncoding, UserBufferMap, VersionPolicy,
+    };
 
     #[test]
     fn test_version() {
-        let version = get_version();
-        assert_eq!(version, Version::parse("2.26.0").unwrap());
+        let version = get_version().unwrap();
+        assert!(VersionPolicy::SameMinor.is_compatible(&SnpeLibrary::header_version(), &version));
     }
 
     #[test]
@@ -191,6 +377,110 @@ mod tests {
         assert!(device.is_available());
     }
 
+    #[test]
+    fn test_build_resnet50() {
+        let container = DlContainer::from_path("test/data/resnet50.dlc").unwrap();
+        let network = Snpe::builder(&container)
+            .runtimes(&[Device::Cpu])
+            .performance_profile(PerformanceProfile::HighPerformance)
+            .build()
+            .unwrap();
+
+        let inputs = network.get_input_tensor_names().unwrap();
+        assert!(inputs.len() > 0);
+    }
+
+    #[test]
+    fn test_execute_resnet50() {
+        let container = DlContainer::from_path("test/data/resnet50.dlc").unwrap();
+        let network = Snpe::builder(&container)
+            .runtimes(&[Device::Cpu])
+            .build()
+            .unwrap();
+
+        let name = network.get_input_tensor_names().unwrap().remove(0);
+        let image = Tensor::from_vec_f32(&vec![0.0; 224 * 224 * 3], &[1, 224, 224, 3]);
+        let inputs = HashMap::from([(name, image)]);
+
+        let outputs: HashMap<String, Tensor> = network.execute(&inputs).unwrap();
+        assert_eq!(outputs.len(), 1);
+
+        let probabilities = outputs.values().next().unwrap();
+        assert_eq!(probabilities.numel(), 1000);
+    }
+
+    #[test]
+    fn test_execute_user_buffers_resnet50() {
+        let container = DlContainer::from_path("test/data/resnet50.dlc").unwrap();
+        let network = Snpe::builder(&container)
+            .runtimes(&[Device::Cpu])
+            .use_user_supplied_buffers(true)
+            .build()
+            .unwrap();
+
+        let input_name = network.get_input_tensor_names().unwrap().remove(0);
+        let output_name = network.get_output_tensor_names().unwrap().remove(0);
+
+        let mut image = vec![0.0f32; 224 * 224 * 3];
+        let mut probabilities = vec![0.0f32; 1000];
+
+        let mut inputs = UserBufferMap::new().unwrap();
+        let strides = packed_strides(&[1, 224, 224, 3], 4);
+        inputs
+            .insert(&input_name, &mut image, &strides, UserBufferEncoding::Float)
+            .unwrap();
+
+        let mut outputs = UserBufferMap::new().unwrap();
+        let strides = packed_strides(&[1, 1000], 4);
+        outputs
+            .insert(
+                &output_name,
+                &mut probabilities,
+                &strides,
+                UserBufferEncoding::Float,
+            )
+            .unwrap();
+
+        // Running twice reuses the same buffers
+        for _ in 0..2 {
+            network.execute_user_buffers(&inputs, &mut outputs).unwrap();
+        }
+
+        let result = outputs.get::<f32>(&output_name).unwrap();
+        let total: f32 = result.iter().sum();
+        assert!((total - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_tensor_info_resnet50() {
+        let container = DlContainer::from_path("test/data/resnet50.dlc").unwrap();
+        let network = Snpe::builder(&container)
+            .runtimes(&[Device::Cpu])
+            .build()
+            .unwrap();
+
+        let inputs = network.input_tensors().unwrap();
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(inputs[0].shape, vec![1, 224, 224, 3]);
+        assert_eq!(inputs[0].element_type, ElementType::Float);
+        assert_eq!(inputs[0].element_size, 4);
+        assert_eq!(inputs[0].encoding(), Some(UserBufferEncoding::Float));
+        assert!(inputs[0].quantization.is_none());
+
+        let outputs = network.output_tensors().unwrap();
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].num_elements(), 1000);
+    }
+
+    #[test]
+    fn test_packed_strides() {
+        assert_eq!(
+            packed_strides(&[1, 224, 224, 3], 4),
+            vec![602112, 2688, 12, 4]
+        );
+        assert_eq!(packed_strides(&[], 4), Vec::<usize>::new());
+    }
+
     #[test]
     #[cfg(all(target_arch = "aarch64", target_os = "windows"))]
     fn test_windows_on_arm() {
@@ -0,0 +1,261 @@
+use std::ffi::CString;
+
+use super::{snpe_bindings, Device, Runtime, RuntimeList, Snpe};
+use crate::dlcontainer::DlContainer;
+use crate::errors::Error;
+
+/// Performance profiles the runtime can be tuned for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PerformanceProfile {
+    /// Balanced between performance and power
+    #[default]
+    Default,
+    /// Maximum performance
+    HighPerformance,
+    /// Lower power at the cost of performance
+    PowerSaver,
+    /// Use the settings of the operating system
+    SystemSettings,
+    /// High performance that can be held for long periods of time
+    SustainedHighPerformance,
+    /// Highest clocks, for short bursts
+    Burst,
+    /// Lower power than the power saver profile
+    LowPowerSaver,
+    /// Higher power than the power saver profile
+    HighPowerSaver,
+    /// Lower power than the balanced profile
+    LowBalanced,
+    /// Lowest power possible
+    ExtremePowerSaver,
+}
+
+impl PerformanceProfile {
+    /// Returns the SNPE performance profile id
+    pub(crate) fn id(&self) -> snpe_bindings::Snpe_PerformanceProfile_t {
+        match self {
+            PerformanceProfile::Default => {
+                snpe_bindings::Snpe_PerformanceProfile_t_SNPE_PERFORMANCE_PROFILE_DEFAULT
+            }
+            PerformanceProfile::HighPerformance => {
+                snpe_bindings::Snpe_PerformanceProfile_t_SNPE_PERFORMANCE_PROFILE_HIGH_PERFORMANCE
+            }
+            PerformanceProfile::PowerSaver => {
+                snpe_bindings::Snpe_PerformanceProfile_t_SNPE_PERFORMANCE_PROFILE_POWER_SAVER
+            }
+            PerformanceProfile::SystemSettings => {
+                snpe_bindings::Snpe_PerformanceProfile_t_SNPE_PERFORMANCE_PROFILE_SYSTEM_SETTINGS
+            }
+            PerformanceProfile::SustainedHighPerformance => {
+                snpe_bindings::Snpe_PerformanceProfile_t_SNPE_PERFORMANCE_PROFILE_SUSTAINED_HIGH_PERFORMANCE
+            }
+            PerformanceProfile::Burst => {
+                snpe_bindings::Snpe_PerformanceProfile_t_SNPE_PERFORMANCE_PROFILE_BURST
+            }
+            PerformanceProfile::LowPowerSaver => {
+                snpe_bindings::Snpe_PerformanceProfile_t_SNPE_PERFORMANCE_PROFILE_LOW_POWER_SAVER
+            }
+            PerformanceProfile::HighPowerSaver => {
+                snpe_bindings::Snpe_PerformanceProfile_t_SNPE_PERFORMANCE_PROFILE_HIGH_POWER_SAVER
+            }
+            PerformanceProfile::LowBalanced => {
+                snpe_bindings::Snpe_PerformanceProfile_t_SNPE_PERFORMANCE_PROFILE_LOW_BALANCED
+            }
+            PerformanceProfile::ExtremePowerSaver => {
+                snpe_bindings::Snpe_PerformanceProfile_t_SNPE_PERFORMANCE_PROFILE_EXTREME_POWER_SAVER
+            }
+        }
+    }
+}
+
+/// Builds a Snpe network from a loaded DlContainer
+pub struct SnpeBuilder<'a> {
+    container: &'a DlContainer,
+    runtimes: Vec<Runtime>,
+    output_layers: Vec<String>,
+    output_tensors: Vec<String>,
+    performance_profile: PerformanceProfile,
+    use_user_supplied_buffers: bool,
+    cpu_fixed_point_mode: bool,
+}
+
+impl<'a> SnpeBuilder<'a> {
+    /// Creates a new builder for the given container. Without any further
+    /// configuration the network runs on the cpu with the default profile.
+    pub fn new(container: &'a DlContainer) -> Self {
+        Self {
+            container,
+            runtimes: vec![],
+            output_layers: vec![],
+            output_tensors: vec![],
+            performance_profile: PerformanceProfile::default(),
+            use_user_supplied_buffers: false,
+            cpu_fixed_point_mode: false,
+        }
+    }
+
+    /// Sets the devices to run on, in order of preference. SNPE falls back to
+    /// the next device for layers the previous one doesn't support.
+    pub fn runtimes(mut self, devices: &[Device]) -> Self {
+        self.runtimes = devices.iter().map(Device::runtime).collect();
+        self
+    }
+
+    /// Sets the exact runtimes to run on, in order of preference. Unlike
+    /// `runtimes` this can select any runtime, e.g. the fp16 gpu runtime.
+    pub fn runtime_order(mut self, runtimes: &[Runtime]) -> Self {
+        self.runtimes = runtimes.to_vec();
+        self
+    }
+
+    /// Sets the runtimes to run on from an existing runtime list
+    pub fn runtime_list(mut self, list: &RuntimeList) -> Self {
+        self.runtimes = list.to_vec();
+        self
+    }
+
+    /// Sets the names of the layers whose outputs should be returned
+    pub fn output_layers<S>(mut self, names: &[S]) -> Self
+    where
+        S: AsRef<str>,
+    {
+        self.output_layers = names.iter().map(|s| s.as_ref().to_string()).collect();
+        self
+    }
+
+    /// Sets the names of the tensors that should be returned
+    pub fn output_tensors<S>(mut self, names: &[S]) -> Self
+    where
+        S: AsRef<str>,
+    {
+        self.output_tensors = names.iter().map(|s| s.as_ref().to_string()).collect();
+        self
+    }
+
+    /// Sets the performance profile of the network
+    pub fn performance_profile(mut self, profile: PerformanceProfile) -> Self {
+        self.performance_profile = profile;
+        self
+    }
+
+    /// Sets whether the network runs on caller supplied buffers, which is
+    /// required to use Snpe::execute_user_buffers
+    pub fn use_user_supplied_buffers(mut self, enabled: bool) -> Self {
+        self.use_user_supplied_buffers = enabled;
+        self
+    }
+
+    /// Sets whether the cpu runtime executes quantized networks in fixed point
+    /// instead of dequantizing them to float
+    pub fn cpu_fixed_point_mode(mut self, enabled: bool) -> Self {
+        self.cpu_fixed_point_mode = enabled;
+        self
+    }
+
+    /// Builds the network
+    pub fn build(self) -> Result<Snpe, Error> {
+        let snpe = unsafe { snpe_bindings::loaded() };
+        let builder = unsafe { snpe.Snpe_SNPEBuilder_Create(self.container.handle) };
+
+        if builder.is_null() {
+            return Err(unsafe { Error::last(&snpe) });
+        }
+
+        // Make sure the builder is freed whether or not configuration succeeds
+        let result = unsafe { self.configure(&snpe, builder) }.and_then(|_| {
+            let handle = unsafe { snpe.Snpe_SNPEBuilder_Build(builder) };
+            if handle.is_null() {
+                return Err(unsafe { Error::last(&snpe) });
+            }
+
+            Ok(Snpe { handle })
+        });
+
+        unsafe {
+            snpe.Snpe_SNPEBuilder_Delete(builder);
+        }
+
+        result
+    }
+
+    /// Applies the builder options to the c builder
+    unsafe fn configure(
+        &self,
+        snpe: &snpe_bindings::SNPE,
+        builder: snpe_bindings::Snpe_SNPEBuilder_Handle_t,
+    ) -> Result<(), Error> {
+        check(
+            snpe,
+            snpe.Snpe_SNPEBuilder_SetPerformanceProfile(builder, self.performance_profile.id()),
+        )?;
+
+        check(
+            snpe,
+            snpe.Snpe_SNPEBuilder_SetUseUserSuppliedBuffers(
+                builder,
+                self.use_user_supplied_buffers as i32,
+            ),
+        )?;
+
+        if self.cpu_fixed_point_mode {
+            check(
+                snpe,
+                snpe.Snpe_SNPEBuilder_SetCpuFixedPointMode(builder, true),
+            )?;
+        }
+
+        if !self.runtimes.is_empty() {
+            let runtime_list = RuntimeList::from_runtimes(&self.runtimes)?;
+            check(
+                snpe,
+                snpe.Snpe_SNPEBuilder_SetRuntimeProcessorOrder(builder, runtime_list.handle()),
+            )?;
+        }
+
+        if !self.output_layers.is_empty() {
+            let names = string_list(snpe, &self.output_layers)?;
+            let code = snpe.Snpe_SNPEBuilder_SetOutputLayers(builder, names);
+            snpe.Snpe_StringList_Delete(names);
+            check(snpe, code)?;
+        }
+
+        if !self.output_tensors.is_empty() {
+            let names = string_list(snpe, &self.output_tensors)?;
+            let code = snpe.Snpe_SNPEBuilder_SetOutputTensors(builder, names);
+            snpe.Snpe_StringList_Delete(names);
+            check(snpe, code)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Converts a failing error code from a setter into an error
+pub(crate) unsafe fn check(
+    snpe: &snpe_bindings::SNPE,
+    code: snpe_bindings::Snpe_ErrorCode_t,
+) -> Result<(), Error> {
+    if code != snpe_bindings::Snpe_ErrorCode_t_SNPE_SUCCESS {
+        return Err(Error::last(&snpe));
+    }
+
+    Ok(())
+}
+
+/// Copies the strings into a new c string list, which the caller must delete
+pub(crate) unsafe fn string_list(
+    snpe: &snpe_bindings::SNPE,
+    strings: &[String],
+) -> Result<snpe_bindings::Snpe_StringList_Handle_t, Error> {
+    let cstrings = strings
+        .iter()
+        .map(|s| CString::new(s.as_str()))
+        .collect::<Result<Vec<CString>, _>>()?;
+
+    let handle = snpe.Snpe_StringList_Create();
+    for cstring in &cstrings {
+        snpe.Snpe_StringList_Append(handle, cstring.as_ptr());
+    }
+
+    Ok(handle)
+}
@@ -0,0 +1,238 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use semver::Version;
+
+use super::{get_version, snpe_bindings};
+use crate::errors::Error;
+
+/// File name of the SNPE library on this platform
+#[cfg(windows)]
+const LIB_NAME: &str = "SNPE.dll";
+#[cfg(not(windows))]
+const LIB_NAME: &str = "libSNPE.so";
+
+/// Variable listing the directories the system searches for shared libraries
+#[cfg(windows)]
+const SEARCH_PATH_VAR: &str = "PATH";
+#[cfg(not(windows))]
+const SEARCH_PATH_VAR: &str = "LD_LIBRARY_PATH";
+
+/// Variable overriding the library to load, either the file or its directory
+pub const OVERRIDE_VAR: &str = "SNPE_LIBRARY_PATH";
+
+/// Version of the sdk headers the bindings were generated from
+const HEADER_VERSION: &str = env!("SNPE_HEADER_VERSION");
+
+/// Policy applied to every library loaded from now on
+static VERSION_POLICY: Mutex<VersionPolicy> = Mutex::new(VersionPolicy::SameMinor);
+
+/// How closely the loaded library has to match the headers the bindings were
+/// generated from. Structs and functions can change between releases, so a
+/// mismatched library can misbehave instead of failing cleanly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum VersionPolicy {
+    /// Major, minor and patch versions must all match
+    Exact,
+    /// Major and minor versions must match
+    #[default]
+    SameMinor,
+    /// Only the major version must match
+    SameMajor,
+}
+
+impl VersionPolicy {
+    /// Returns if a library of the found version satisfies the policy
+    pub fn is_compatible(&self, expected: &Version, found: &Version) -> bool {
+        match self {
+            VersionPolicy::Exact => {
+                (expected.major, expected.minor, expected.patch)
+                    == (found.major, found.minor, found.patch)
+            }
+            VersionPolicy::SameMinor => {
+                expected.major == found.major && expected.minor == found.minor
+            }
+            VersionPolicy::SameMajor => expected.major == found.major,
+        }
+    }
+}
+
+/// The SNPE library loaded into this process. Only one library can be loaded
+/// at a time, and it stays loaded until the process exits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SnpeLibrary {
+    path: &'static Path,
+}
+
+impl SnpeLibrary {
+    /// Loads the library at the given path. Opening the file that's already
+    /// loaded returns it, while any other file fails.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+
+        if let Some(current) = Self::current() {
+            if current.path == path {
+                return Ok(current);
+            }
+
+            return Err(Error::InvalidState(format!(
+                "SNPE library is already loaded from {}",
+                current.path.display()
+            )));
+        }
+
+        unsafe { snpe_bindings::load(path)? };
+        Self::current()
+            .ok_or_else(|| Error::InvalidState("SNPE library failed to load".to_string()))
+    }
+
+    /// Loads the library from the environment. If SNPE_LIBRARY_PATH is set
+    /// only that library is tried, otherwise the first library found in the
+    /// executable's directory, SNPE_ROOT, LD_LIBRARY_PATH or the build time
+    /// sdk is loaded.
+    pub fn from_env() -> Result<Self, Error> {
+        if let Some(current) = Self::current() {
+            return Ok(current);
+        }
+
+        if let Some(path) = env::var_os(OVERRIDE_VAR) {
+            let path = PathBuf::from(path);
+            if path.is_dir() {
+                return Self::open(path.join(LIB_NAME));
+            }
+
+            return Self::open(path);
+        }
+
+        let candidates = Self::candidates();
+        match candidates.iter().find(|path| path.is_file()) {
+            Some(path) => Self::open(path),
+            None => Err(Error::LibraryNotFound(
+                candidates
+                    .iter()
+                    .map(|path| path.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            )),
+        }
+    }
+
+    /// Sets the policy used to check the version of libraries loaded from
+    /// now on, including the one loaded implicitly on first use
+    pub fn set_version_policy(policy: VersionPolicy) {
+        *VERSION_POLICY.lock().unwrap_or_else(|e| e.into_inner()) = policy;
+    }
+
+    /// Returns the policy used to check the version of loaded libraries
+    pub fn version_policy() -> VersionPolicy {
+        *VERSION_POLICY.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Returns the version of the sdk headers the bindings were generated from
+    pub fn header_version() -> Version {
+        Version::parse(HEADER_VERSION).expect("build.rs emits a valid header version")
+    }
+
+    /// Returns the library currently loaded, if any
+    pub fn current() -> Option<Self> {
+        snpe_bindings::loaded_path().map(|path| Self { path })
+    }
+
+    /// Returns the paths searched by from_env, in order
+    pub fn candidates() -> Vec<PathBuf> {
+        let mut paths = vec![];
+
+        let exe_dir = env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_path_buf));
+        if let Some(dir) = exe_dir {
+            paths.push(dir.join(LIB_NAME));
+        }
+
+        if let Some(root) = env::var_os("SNPE_ROOT") {
+            let lib_dir = PathBuf::from(root)
+                .join("lib")
+                .join(env!("SNPE_PLATFORM_DIR"));
+            paths.push(lib_dir.join(LIB_NAME));
+        }
+
+        if let Some(dirs) = env::var_os(SEARCH_PATH_VAR) {
+            paths.extend(env::split_paths(&dirs).map(|dir| dir.join(LIB_NAME)));
+        }
+
+        // Builds with bundled bindings may not have had an sdk
+        if !env!("SNPE_LIB_DIR").is_empty() {
+            paths.push(PathBuf::from(snpe_bindings::LIB));
+        }
+
+        paths
+    }
+
+    /// Returns the file the library was loaded from
+    pub fn path(&self) -> &'static Path {
+        self.path
+    }
+
+    /// Returns the version reported by the library
+    pub fn version(&self) -> Result<Version, Error> {
+        get_version()
+    }
+}
+
+/// Checks a library's version against the headers using the current policy
+pub(crate) fn check_version(found: &Version) -> Result<(), Error> {
+    let expected = SnpeLibrary::header_version();
+
+    if !SnpeLibrary::version_policy().is_compatible(&expected, found) {
+        return Err(Error::IncompatibleVersion {
+            expected,
+            found: found.clone(),
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use semver::Version;
+
+    use super::{SnpeLibrary, VersionPolicy};
+    use crate::snpe::snpe_bindings;
+
+    #[test]
+    fn build_sdk_searched_last() {
+        let candidates = SnpeLibrary::candidates();
+        let build_lib = PathBuf::from(snpe_bindings::LIB);
+
+        match env!("SNPE_LIB_DIR").is_empty() {
+            true => assert!(!candidates.contains(&build_lib)),
+            false => assert_eq!(candidates.last(), Some(&build_lib)),
+        }
+    }
+
+    #[test]
+    fn version_policies() {
+        let expected = Version::parse("2.26.0").unwrap();
+        let patch = Version::parse("2.26.2").unwrap();
+        let minor = Version::parse("2.18.0").unwrap();
+        let major = Version::parse("3.0.0").unwrap();
+
+        assert!(VersionPolicy::Exact.is_compatible(&expected, &expected));
+        assert!(!VersionPolicy::Exact.is_compatible(&expected, &patch));
+
+        assert!(VersionPolicy::SameMinor.is_compatible(&expected, &patch));
+        assert!(!VersionPolicy::SameMinor.is_compatible(&expected, &minor));
+
+        assert!(VersionPolicy::SameMajor.is_compatible(&expected, &minor));
+        assert!(!VersionPolicy::SameMajor.is_compatible(&expected, &major));
+    }
+
+    #[test]
+    fn open_missing_library() {
+        assert!(SnpeLibrary::open("/does/not/exist/libSNPE.so").is_err());
+    }
+}
@@ -0,0 +1,239 @@
+use std::fmt;
+use std::str::FromStr;
+
+use super::snpe_bindings;
+use crate::errors::Error;
+
+/// Runtimes supported by SNPE, each a device paired with the precision it computes in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Runtime {
+    /// Cpu with 32 bit float math
+    CpuFloat32,
+    /// Adreno gpu with 16 bit float storage and 32 bit float math
+    GpuFloat32Float16Hybrid,
+    /// Hexagon dsp with 8 bit fixed point math
+    DspFixed8Tf,
+    /// Adreno gpu with 16 bit float storage and math
+    GpuFloat16,
+    /// Snapdragon AIX+HVX with 8 bit fixed point math
+    AipFixed8Tf,
+    /// No runtime selected
+    Unset,
+}
+
+impl Runtime {
+    /// Default cpu runtime
+    pub const CPU: Runtime = Runtime::CpuFloat32;
+    /// Default gpu runtime
+    pub const GPU: Runtime = Runtime::GpuFloat32Float16Hybrid;
+    /// Default dsp runtime
+    pub const DSP: Runtime = Runtime::DspFixed8Tf;
+    /// Default aip runtime
+    pub const AIP: Runtime = Runtime::AipFixed8Tf;
+
+    /// Every runtime that can execute a network
+    pub const ALL: [Runtime; 5] = [
+        Runtime::CpuFloat32,
+        Runtime::GpuFloat32Float16Hybrid,
+        Runtime::DspFixed8Tf,
+        Runtime::GpuFloat16,
+        Runtime::AipFixed8Tf,
+    ];
+
+    /// Returns the SNPE runtime id
+    pub(crate) fn id(&self) -> snpe_bindings::Snpe_Runtime_t {
+        match self {
+            Runtime::CpuFloat32 => snpe_bindings::Snpe_Runtime_t_SNPE_RUNTIME_CPU_FLOAT32,
+            Runtime::GpuFloat32Float16Hybrid => {
+                snpe_bindings::Snpe_Runtime_t_SNPE_RUNTIME_GPU_FLOAT32_16_HYBRID
+            }
+            Runtime::DspFixed8Tf => snpe_bindings::Snpe_Runtime_t_SNPE_RUNTIME_DSP_FIXED8_TF,
+            Runtime::GpuFloat16 => snpe_bindings::Snpe_Runtime_t_SNPE_RUNTIME_GPU_FLOAT16,
+            Runtime::AipFixed8Tf => snpe_bindings::Snpe_Runtime_t_SNPE_RUNTIME_AIP_FIXED8_TF,
+            Runtime::Unset => snpe_bindings::Snpe_Runtime_t_SNPE_RUNTIME_UNSET,
+        }
+    }
+
+    /// Converts an SNPE runtime id, mapping unknown ids to Unset
+    pub(crate) fn from_id(id: snpe_bindings::Snpe_Runtime_t) -> Self {
+        Runtime::ALL
+            .into_iter()
+            .find(|runtime| runtime.id() == id)
+            .unwrap_or(Runtime::Unset)
+    }
+
+    /// Returns the name SNPE tools use for the runtime
+    pub fn name(&self) -> &'static str {
+        match self {
+            Runtime::CpuFloat32 => "cpu_float32",
+            Runtime::GpuFloat32Float16Hybrid => "gpu_float32_16_hybrid",
+            Runtime::DspFixed8Tf => "dsp_fixed8_tf",
+            Runtime::GpuFloat16 => "gpu_float16",
+            Runtime::AipFixed8Tf => "aip_fixed8_tf",
+            Runtime::Unset => "unset",
+        }
+    }
+
+    /// Returns if the runtime is available on this machine. Nothing is
+    /// available if the library can't be loaded.
+    pub fn is_available(&self) -> bool {
+        unsafe {
+            match snpe_bindings::get() {
+                Ok(snpe) => snpe.Snpe_Util_IsRuntimeAvailable(self.id()) != 0,
+                Err(_) => false,
+            }
+        }
+    }
+}
+
+impl fmt::Display for Runtime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Runtime {
+    type Err = Error;
+
+    /// Parses the full runtime names as well as the short cpu/gpu/dsp/aip aliases
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "cpu" | "cpu_float32" => Ok(Runtime::CpuFloat32),
+            "gpu" | "gpu_float32_16_hybrid" => Ok(Runtime::GpuFloat32Float16Hybrid),
+            "dsp" | "dsp_fixed8_tf" => Ok(Runtime::DspFixed8Tf),
+            "gpu_float16" => Ok(Runtime::GpuFloat16),
+            "aip" | "aip_fixed8_tf" | "aip_fixed_tf" => Ok(Runtime::AipFixed8Tf),
+            "unset" => Ok(Runtime::Unset),
+            _ => Err(Error::InvalidArgument(format!("Unknown runtime {}", s))),
+        }
+    }
+}
+
+/// Ordered list of runtimes. SNPE runs each layer on the first runtime in
+/// the list that supports it, falling back to the next ones in order.
+pub struct RuntimeList {
+    handle: snpe_bindings::Snpe_RuntimeList_Handle_t,
+}
+
+impl RuntimeList {
+    /// Creates a new empty runtime list
+    pub fn new() -> Result<Self, Error> {
+        let snpe = unsafe { snpe_bindings::get()? };
+        let handle = unsafe { snpe.Snpe_RuntimeList_Create() };
+
+        if handle.is_null() {
+            return Err(unsafe { Error::last(&snpe) });
+        }
+
+        Ok(Self { handle })
+    }
+
+    /// Creates a runtime list with the runtimes in the given order
+    pub fn from_runtimes(runtimes: &[Runtime]) -> Result<Self, Error> {
+        let mut list = Self::new()?;
+        for runtime in runtimes {
+            list.push(*runtime)?;
+        }
+
+        Ok(list)
+    }
+
+    /// Returns the internal handle to the c object
+    pub(crate) fn handle(&self) -> snpe_bindings::Snpe_RuntimeList_Handle_t {
+        self.handle
+    }
+
+    /// Appends a runtime as the lowest priority fallback. Fails if the
+    /// runtime is already in the list.
+    pub fn push(&mut self, runtime: Runtime) -> Result<(), Error> {
+        unsafe {
+            let snpe = snpe_bindings::loaded();
+            let code = snpe.Snpe_RuntimeList_Add(self.handle, runtime.id());
+
+            if code != 0 {
+                return Err(Error::last(&snpe));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of runtimes in the list
+    pub fn len(&self) -> usize {
+        unsafe {
+            let snpe = snpe_bindings::loaded();
+            snpe.Snpe_RuntimeList_Size(self.handle)
+        }
+    }
+
+    /// Returns if the list is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the runtime at the given position
+    pub fn get(&self, index: usize) -> Option<Runtime> {
+        if index >= self.len() {
+            return None;
+        }
+
+        unsafe {
+            let snpe = snpe_bindings::loaded();
+            let id = snpe.Snpe_RuntimeList_GetRuntime(self.handle, index as i32);
+            Some(Runtime::from_id(id))
+        }
+    }
+
+    /// Copies the runtimes out in priority order
+    pub fn to_vec(&self) -> Vec<Runtime> {
+        (0..self.len()).filter_map(|i| self.get(i)).collect()
+    }
+}
+
+impl Drop for RuntimeList {
+    fn drop(&mut self) {
+        unsafe {
+            let snpe = snpe_bindings::loaded();
+            snpe.Snpe_RuntimeList_Delete(self.handle);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Runtime, RuntimeList};
+
+    #[test]
+    fn runtime_ids() {
+        for runtime in Runtime::ALL {
+            assert_eq!(Runtime::from_id(runtime.id()), runtime);
+        }
+
+        assert_eq!(Runtime::from_id(Runtime::Unset.id()), Runtime::Unset);
+    }
+
+    #[test]
+    fn parse_runtimes() {
+        assert_eq!("cpu".parse::<Runtime>().unwrap(), Runtime::CPU);
+        assert_eq!(
+            "GPU_FLOAT16".parse::<Runtime>().unwrap(),
+            Runtime::GpuFloat16
+        );
+        assert_eq!("dsp".parse::<Runtime>().unwrap(), Runtime::DspFixed8Tf);
+        assert!("tpu".parse::<Runtime>().is_err());
+
+        for runtime in Runtime::ALL {
+            assert_eq!(runtime.name().parse::<Runtime>().unwrap(), runtime);
+        }
+    }
+
+    #[test]
+    fn runtime_list_order() {
+        let order = [Runtime::DSP, Runtime::GpuFloat16, Runtime::CPU];
+        let list = RuntimeList::from_runtimes(&order).unwrap();
+
+        assert_eq!(list.len(), 3);
+        assert_eq!(list.to_vec(), order.to_vec());
+        assert_eq!(list.get(3), None);
+    }
+}
@@ -0,0 +1,573 @@
+#[cfg(feature = "ndarray")]
+mod array;
+#[cfg(feature = "tch")]
+mod torch;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::mem::size_of;
+
+use tensor_rs::tensor::Tensor;
+
+use super::snpe_bindings;
+use super::tensor_info::{read_shape, ElementType};
+use crate::errors::Error;
+
+/// Elements of a SnpeTensor
+#[derive(Debug, Clone, PartialEq)]
+pub enum TensorData {
+    Float(Vec<f32>),
+    Uint8(Vec<u8>),
+    Uint16(Vec<u16>),
+    Int32(Vec<i32>),
+}
+
+impl TensorData {
+    /// Returns the number of elements
+    pub fn len(&self) -> usize {
+        match self {
+            TensorData::Float(values) => values.len(),
+            TensorData::Uint8(values) => values.len(),
+            TensorData::Uint16(values) => values.len(),
+            TensorData::Int32(values) => values.len(),
+        }
+    }
+
+    /// Returns if there are no elements
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the elements as native endian bytes
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            TensorData::Float(values) => as_bytes(values),
+            TensorData::Uint8(values) => values,
+            TensorData::Uint16(values) => as_bytes(values),
+            TensorData::Int32(values) => as_bytes(values),
+        }
+    }
+
+    /// Rearranges the elements of a row major tensor of the given shape
+    fn permute(&self, shape: &[usize], order: &[usize]) -> TensorData {
+        match self {
+            TensorData::Float(values) => TensorData::Float(permute(values, shape, order)),
+            TensorData::Uint8(values) => TensorData::Uint8(permute(values, shape, order)),
+            TensorData::Uint16(values) => TensorData::Uint16(permute(values, shape, order)),
+            TensorData::Int32(values) => TensorData::Int32(permute(values, shape, order)),
+        }
+    }
+}
+
+/// Owned tensor in row major order, the form the crate passes tensors to SNPE in
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnpeTensor {
+    shape: Vec<usize>,
+    data: TensorData,
+}
+
+impl SnpeTensor {
+    /// Creates a tensor, checking that the data fills the shape
+    pub fn new(shape: &[usize], data: TensorData) -> Result<Self, Error> {
+        let expected: usize = shape.iter().product();
+        if expected != data.len() {
+            return Err(Error::ShapeMismatch(format!(
+                "Shape {:?} holds {} elements but got {}",
+                shape,
+                expected,
+                data.len()
+            )));
+        }
+
+        Ok(Self {
+            shape: shape.to_vec(),
+            data,
+        })
+    }
+
+    /// Creates a tensor from row major elements stored as native endian bytes
+    pub fn from_bytes(
+        shape: &[usize],
+        element_type: ElementType,
+        bytes: &[u8],
+    ) -> Result<Self, Error> {
+        let data = match element_type {
+            ElementType::Float => TensorData::Float(from_bytes(bytes, f32::from_ne_bytes)?),
+            ElementType::Uint8 | ElementType::Unsigned8Bit => TensorData::Uint8(bytes.to_vec()),
+            ElementType::Uint16 => TensorData::Uint16(from_bytes(bytes, u16::from_ne_bytes)?),
+            ElementType::Int32 => TensorData::Int32(from_bytes(bytes, i32::from_ne_bytes)?),
+            other => {
+                return Err(Error::UnsupportedElementType(format!(
+                    "{:?} tensors",
+                    other
+                )))
+            }
+        };
+
+        Self::new(shape, data)
+    }
+
+    /// Returns the dimensions of the tensor
+    pub fn shape(&self) -> &[usize] {
+        &self.shape
+    }
+
+    /// Returns the elements of the tensor
+    pub fn data(&self) -> &TensorData {
+        &self.data
+    }
+
+    /// Consumes the tensor, returning its elements
+    pub fn into_data(self) -> TensorData {
+        self.data
+    }
+
+    /// Returns the type of the elements
+    pub fn element_type(&self) -> ElementType {
+        match self.data {
+            TensorData::Float(_) => ElementType::Float,
+            TensorData::Uint8(_) => ElementType::Uint8,
+            TensorData::Uint16(_) => ElementType::Uint16,
+            TensorData::Int32(_) => ElementType::Int32,
+        }
+    }
+
+    /// Returns the number of elements in the tensor
+    pub fn num_elements(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Returns a copy of the elements converted to floats
+    pub fn to_f32(&self) -> Vec<f32> {
+        match &self.data {
+            TensorData::Float(values) => values.clone(),
+            TensorData::Uint8(values) => values.iter().map(|&v| v as f32).collect(),
+            TensorData::Uint16(values) => values.iter().map(|&v| v as f32).collect(),
+            TensorData::Int32(values) => values.iter().map(|&v| v as f32).collect(),
+        }
+    }
+
+    /// Returns a copy with the dimensions reordered, so that dimension i of
+    /// the result is dimension order[i] of this tensor
+    pub fn permute(&self, order: &[usize]) -> Result<Self, Error> {
+        let mut sorted = order.to_vec();
+        sorted.sort_unstable();
+        if !sorted.iter().copied().eq(0..self.shape.len()) {
+            return Err(Error::InvalidArgument(format!(
+                "{:?} is not a permutation of {} dimensions",
+                order,
+                self.shape.len()
+            )));
+        }
+
+        Ok(Self {
+            shape: order.iter().map(|&i| self.shape[i]).collect(),
+            data: self.data.permute(&self.shape, order),
+        })
+    }
+
+    /// Converts an image batch from the NCHW layout used by PyTorch to the
+    /// NHWC layout SNPE expects
+    pub fn nchw_to_nhwc(&self) -> Result<Self, Error> {
+        self.check_rank(4)?;
+        self.permute(&[0, 2, 3, 1])
+    }
+
+    /// Converts an image batch from NHWC back to NCHW
+    pub fn nhwc_to_nchw(&self) -> Result<Self, Error> {
+        self.check_rank(4)?;
+        self.permute(&[0, 3, 1, 2])
+    }
+
+    fn check_rank(&self, rank: usize) -> Result<(), Error> {
+        if self.shape.len() != rank {
+            return Err(Error::ShapeMismatch(format!(
+                "Expected {} dimensions but the shape is {:?}",
+                rank, self.shape
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+/// Tensors that can be passed to SNPE. Implement it to execute networks on
+/// your own tensor type.
+pub trait AsSnpeTensor {
+    /// Returns the dimensions of the tensor
+    fn shape(&self) -> Vec<usize>;
+
+    /// Returns the type of the elements
+    fn element_type(&self) -> ElementType;
+
+    /// Returns the elements in row major order as native endian bytes,
+    /// failing if they can't be converted to the element type
+    fn bytes(&self) -> Result<Cow<'_, [u8]>, Error>;
+
+    /// Copies the tensor into the form the crate hands to SNPE
+    fn to_snpe_tensor(&self) -> Result<SnpeTensor, Error> {
+        SnpeTensor::from_bytes(&self.shape(), self.element_type(), &self.bytes()?)
+    }
+}
+
+/// Tensors that SNPE outputs can be converted into
+pub trait FromSnpeTensor: Sized {
+    /// Converts an output tensor, failing if its elements can't be represented
+    fn from_snpe_tensor(tensor: SnpeTensor) -> Result<Self, Error>;
+}
+
+impl<T: AsSnpeTensor + ?Sized> AsSnpeTensor for &T {
+    fn shape(&self) -> Vec<usize> {
+        (**self).shape()
+    }
+
+    fn element_type(&self) -> ElementType {
+        (**self).element_type()
+    }
+
+    fn bytes(&self) -> Result<Cow<'_, [u8]>, Error> {
+        (**self).bytes()
+    }
+
+    fn to_snpe_tensor(&self) -> Result<SnpeTensor, Error> {
+        (**self).to_snpe_tensor()
+    }
+}
+
+impl AsSnpeTensor for SnpeTensor {
+    fn shape(&self) -> Vec<usize> {
+        self.shape.clone()
+    }
+
+    fn element_type(&self) -> ElementType {
+        SnpeTensor::element_type(self)
+    }
+
+    fn bytes(&self) -> Result<Cow<'_, [u8]>, Error> {
+        Ok(Cow::Borrowed(self.data.as_bytes()))
+    }
+
+    fn to_snpe_tensor(&self) -> Result<SnpeTensor, Error> {
+        Ok(self.clone())
+    }
+}
+
+impl FromSnpeTensor for SnpeTensor {
+    fn from_snpe_tensor(tensor: SnpeTensor) -> Result<Self, Error> {
+        Ok(tensor)
+    }
+}
+
+impl AsSnpeTensor for Tensor {
+    fn shape(&self) -> Vec<usize> {
+        self.size()
+    }
+
+    fn element_type(&self) -> ElementType {
+        ElementType::Float
+    }
+
+    fn bytes(&self) -> Result<Cow<'_, [u8]>, Error> {
+        Ok(Cow::Owned(as_bytes(&self.get_data_f32()).to_vec()))
+    }
+
+    fn to_snpe_tensor(&self) -> Result<SnpeTensor, Error> {
+        Ok(SnpeTensor::from(self))
+    }
+}
+
+impl FromSnpeTensor for Tensor {
+    fn from_snpe_tensor(tensor: SnpeTensor) -> Result<Self, Error> {
+        Ok(Tensor::from(&tensor))
+    }
+}
+
+/// Plain floats in row major order along with their shape
+impl AsSnpeTensor for (Vec<f32>, Vec<usize>) {
+    fn shape(&self) -> Vec<usize> {
+        self.1.clone()
+    }
+
+    fn element_type(&self) -> ElementType {
+        ElementType::Float
+    }
+
+    fn bytes(&self) -> Result<Cow<'_, [u8]>, Error> {
+        Ok(Cow::Borrowed(as_bytes(&self.0)))
+    }
+}
+
+impl FromSnpeTensor for (Vec<f32>, Vec<usize>) {
+    fn from_snpe_tensor(tensor: SnpeTensor) -> Result<Self, Error> {
+        Ok((tensor.to_f32(), tensor.shape))
+    }
+}
+
+impl From<&Tensor> for SnpeTensor {
+    fn from(tensor: &Tensor) -> Self {
+        Self {
+            shape: tensor.size(),
+            data: TensorData::Float(tensor.get_data_f32()),
+        }
+    }
+}
+
+impl From<&SnpeTensor> for Tensor {
+    fn from(tensor: &SnpeTensor) -> Self {
+        Tensor::from_vec_f32(&tensor.to_f32(), tensor.shape())
+    }
+}
+
+/// Numbers whose memory is nothing but their native endian bytes
+pub(crate) trait PlainElement: Copy {}
+
+impl PlainElement for f32 {}
+impl PlainElement for u8 {}
+impl PlainElement for u16 {}
+impl PlainElement for i32 {}
+
+/// Reinterprets numbers as their native endian bytes
+pub(crate) fn as_bytes<T: PlainElement>(values: &[T]) -> &[u8] {
+    unsafe {
+        std::slice::from_raw_parts(values.as_ptr() as *const u8, size_of::<T>() * values.len())
+    }
+}
+
+/// Decodes native endian bytes into numbers of N bytes each
+fn from_bytes<T, const N: usize>(bytes: &[u8], decode: fn([u8; N]) -> T) -> Result<Vec<T>, Error> {
+    if bytes.len() % N != 0 {
+        return Err(Error::ShapeMismatch(format!(
+            "{} bytes isn't a whole number of {} byte elements",
+            bytes.len(),
+            N
+        )));
+    }
+
+    Ok(bytes
+        .chunks_exact(N)
+        .map(|chunk| decode(chunk.try_into().unwrap()))
+        .collect())
+}
+
+/// Copies the elements of a row major tensor into the order given by permuting
+/// its dimensions
+fn permute<T: Copy>(values: &[T], shape: &[usize], order: &[usize]) -> Vec<T> {
+    // Strides of the source dimensions, visited in the order of the result
+    let mut strides = vec![1; shape.len()];
+    for i in (0..shape.len().saturating_sub(1)).rev() {
+        strides[i] = strides[i + 1] * shape[i + 1];
+    }
+
+    let dims: Vec<usize> = order.iter().map(|&i| shape[i]).collect();
+    let steps: Vec<usize> = order.iter().map(|&i| strides[i]).collect();
+
+    let mut result = Vec::with_capacity(values.len());
+    let mut index = vec![0; dims.len()];
+    for _ in 0..values.len() {
+        let offset: usize = index.iter().zip(&steps).map(|(i, step)| i * step).sum();
+        result.push(values[offset]);
+
+        // Advance the index of the result like an odometer
+        for d in (0..dims.len()).rev() {
+            index[d] += 1;
+            if index[d] < dims[d] {
+                break;
+            }
+            index[d] = 0;
+        }
+    }
+
+    result
+}
+
+/// Owned c tensor map, along with any tensors we allocated for it
+pub(crate) struct TensorMap {
+    handle: snpe_bindings::Snpe_TensorMap_Handle_t,
+
+    /// The map only holds references, so we're responsible for freeing these
+    tensors: Vec<snpe_bindings::Snpe_ITensor_Handle_t>,
+}
+
+impl TensorMap {
+    /// Creates a new empty tensor map
+    pub(crate) fn new() -> Result<Self, Error> {
+        let snpe = unsafe { snpe_bindings::get()? };
+        let handle = unsafe { snpe.Snpe_TensorMap_Create() };
+
+        if handle.is_null() {
+            return Err(unsafe { Error::last(&snpe) });
+        }
+
+        Ok(Self {
+            handle,
+            tensors: vec![],
+        })
+    }
+
+    /// Returns the internal handle to the c object
+    pub(crate) fn handle(&self) -> snpe_bindings::Snpe_TensorMap_Handle_t {
+        self.handle
+    }
+
+    /// Copies the tensor into a new ITensor and adds it to the map. ITensors
+    /// hold floats, so other element types are converted.
+    pub(crate) fn insert<T>(&mut self, name: &str, tensor: &T) -> Result<(), Error>
+    where
+        T: AsSnpeTensor + ?Sized,
+    {
+        let cname = CString::new(name)?;
+        let dims = AsSnpeTensor::shape(tensor);
+
+        // Float bytes can go straight into the ITensor
+        let data = match AsSnpeTensor::element_type(tensor) {
+            ElementType::Float => tensor.bytes()?,
+            _ => Cow::Owned(as_bytes(&tensor.to_snpe_tensor()?.to_f32()).to_vec()),
+        };
+        let len = data.len() / size_of::<f32>();
+
+        unsafe {
+            let snpe = snpe_bindings::loaded();
+            let shape = snpe.Snpe_TensorShape_CreateDimsSize(dims.as_ptr(), dims.len());
+            let itensor = snpe.Snpe_Util_CreateITensor(shape);
+            snpe.Snpe_TensorShape_Delete(shape);
+
+            if itensor.is_null() {
+                return Err(Error::last(&snpe));
+            }
+
+            // Track the tensor right away so it's freed even if the copy fails
+            self.tensors.push(itensor);
+
+            let size = snpe.Snpe_ITensor_GetSize(itensor);
+            if size != len {
+                return Err(Error::ShapeMismatch(format!(
+                    "Tensor {} has {} elements but its shape {:?} holds {}",
+                    name, len, dims, size
+                )));
+            }
+
+            let dst = snpe.Snpe_ITensor_GetData(itensor) as *mut u8;
+            std::ptr::copy_nonoverlapping(data.as_ptr(), dst, size * size_of::<f32>());
+
+            snpe.Snpe_TensorMap_Add(self.handle, cname.as_ptr(), itensor);
+        }
+
+        Ok(())
+    }
+
+    /// Copies every tensor in the map back into rust
+    pub(crate) fn to_tensors(&self) -> Result<HashMap<String, SnpeTensor>, Error> {
+        let mut result = HashMap::new();
+
+        unsafe {
+            let snpe = snpe_bindings::loaded();
+            let names = snpe.Snpe_TensorMap_GetTensorNames(self.handle);
+
+            if names.is_null() {
+                return Err(Error::last(&snpe));
+            }
+
+            let n = snpe.Snpe_StringList_Size(names);
+            for i in 0..n {
+                let cname = CStr::from_ptr(snpe.Snpe_StringList_At(names, i));
+                let itensor = snpe.Snpe_TensorMap_GetTensor_Ref(self.handle, cname.as_ptr());
+
+                // Read the dimensions of the output
+                let shape = snpe.Snpe_ITensor_GetShape(itensor);
+                let dims = read_shape(&snpe, shape);
+                snpe.Snpe_TensorShape_Delete(shape);
+
+                let size = snpe.Snpe_ITensor_GetSize(itensor);
+                let data = snpe.Snpe_ITensor_GetData(itensor) as *const f32;
+                let values = std::slice::from_raw_parts(data, size);
+
+                result.insert(
+                    cname.to_string_lossy().to_string(),
+                    SnpeTensor {
+                        shape: dims,
+                        data: TensorData::Float(values.to_vec()),
+                    },
+                );
+            }
+
+            snpe.Snpe_StringList_Delete(names);
+        }
+
+        Ok(result)
+    }
+}
+
+impl Drop for TensorMap {
+    fn drop(&mut self) {
+        unsafe {
+            let snpe = snpe_bindings::loaded();
+            snpe.Snpe_TensorMap_Delete(self.handle);
+
+            for itensor in &self.tensors {
+                snpe.Snpe_ITensor_Delete(*itensor);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AsSnpeTensor, ElementType, FromSnpeTensor, SnpeTensor, TensorData};
+
+    #[test]
+    fn shape_must_fit_data() {
+        assert!(SnpeTensor::new(&[2, 3], TensorData::Uint8(vec![0; 6])).is_ok());
+        assert!(SnpeTensor::new(&[2, 3], TensorData::Uint8(vec![0; 5])).is_err());
+    }
+
+    #[test]
+    fn nchw_round_trip() {
+        // One image with 2 channels of 2x3 pixels, channel c pixel p is 10c + p
+        let values = (0..2)
+            .flat_map(|c| (0..6).map(move |p| (10 * c + p) as i32))
+            .collect();
+        let nchw = SnpeTensor::new(&[1, 2, 2, 3], TensorData::Int32(values)).unwrap();
+
+        let nhwc = nchw.nchw_to_nhwc().unwrap();
+        assert_eq!(nhwc.shape(), &[1, 2, 3, 2]);
+        assert_eq!(
+            nhwc.data(),
+            &TensorData::Int32(vec![0, 10, 1, 11, 2, 12, 3, 13, 4, 14, 5, 15])
+        );
+
+        assert_eq!(nhwc.nhwc_to_nchw().unwrap(), nchw);
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let tensor = SnpeTensor::new(&[3], TensorData::Uint16(vec![1, 256, 65535])).unwrap();
+        let copy = SnpeTensor::from_bytes(
+            &[3],
+            ElementType::Uint16,
+            &AsSnpeTensor::bytes(&tensor).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(copy, tensor);
+
+        assert!(SnpeTensor::from_bytes(&[1], ElementType::Float, &[0; 3]).is_err());
+        assert!(SnpeTensor::from_bytes(&[1], ElementType::Int64, &[0; 8]).is_err());
+    }
+
+    #[test]
+    fn plain_vectors() {
+        let input = (vec![1.0, 2.0, 3.0, 4.0], vec![2, 2]);
+        let tensor = input.to_snpe_tensor().unwrap();
+        assert_eq!(tensor.shape(), &[2, 2]);
+
+        let output = <(Vec<f32>, Vec<usize>)>::from_snpe_tensor(tensor).unwrap();
+        assert_eq!(output, input);
+    }
+
+    #[test]
+    fn invalid_permutation() {
+        let tensor = SnpeTensor::new(&[2, 2], TensorData::Float(vec![0.0; 4])).unwrap();
+        assert!(tensor.permute(&[0, 0]).is_err());
+        assert!(tensor.nchw_to_nhwc().is_err());
+    }
+}
@@ -0,0 +1,201 @@
+use std::borrow::Cow;
+use std::mem::size_of;
+
+use ndarray::{ArrayD, ArrayViewD, ArrayViewMutD, IxDyn};
+
+use super::{as_bytes, AsSnpeTensor, FromSnpeTensor, PlainElement, SnpeTensor, TensorData};
+use crate::errors::Error;
+use crate::snpe::{ElementType, UserBufferElement, UserBufferEncoding, UserBufferMap};
+
+impl From<ArrayViewD<'_, f32>> for SnpeTensor {
+    /// Copies the elements in row major order, whatever the layout of the view
+    fn from(view: ArrayViewD<'_, f32>) -> Self {
+        Self {
+            shape: view.shape().to_vec(),
+            data: TensorData::Float(view.iter().copied().collect()),
+        }
+    }
+}
+
+impl From<ArrayViewD<'_, u8>> for SnpeTensor {
+    /// Copies the elements in row major order, whatever the layout of the view
+    fn from(view: ArrayViewD<'_, u8>) -> Self {
+        Self {
+            shape: view.shape().to_vec(),
+            data: TensorData::Uint8(view.iter().copied().collect()),
+        }
+    }
+}
+
+impl TryFrom<SnpeTensor> for ArrayD<f32> {
+    type Error = Error;
+
+    fn try_from(tensor: SnpeTensor) -> Result<Self, Self::Error> {
+        match tensor.data {
+            TensorData::Float(values) => to_array(&tensor.shape, values),
+            _ => Err(Error::UnsupportedElementType(format!(
+                "{:?} tensors can't become f32 arrays",
+                tensor.element_type()
+            ))),
+        }
+    }
+}
+
+impl TryFrom<SnpeTensor> for ArrayD<u8> {
+    type Error = Error;
+
+    fn try_from(tensor: SnpeTensor) -> Result<Self, Self::Error> {
+        match tensor.data {
+            TensorData::Uint8(values) => to_array(&tensor.shape, values),
+            _ => Err(Error::UnsupportedElementType(format!(
+                "{:?} tensors can't become u8 arrays",
+                tensor.element_type()
+            ))),
+        }
+    }
+}
+
+/// Implements the tensor traits for f32 or u8 arrays and their views
+macro_rules! impl_array_tensor {
+    ($element:ty, $element_type:expr) => {
+        impl AsSnpeTensor for ArrayViewD<'_, $element> {
+            fn shape(&self) -> Vec<usize> {
+                ArrayViewD::shape(self).to_vec()
+            }
+
+            fn element_type(&self) -> ElementType {
+                $element_type
+            }
+
+            /// Borrows the memory of standard layout views, copies others
+            fn bytes(&self) -> Result<Cow<'_, [u8]>, Error> {
+                Ok(array_bytes(self))
+            }
+        }
+
+        impl AsSnpeTensor for ArrayD<$element> {
+            fn shape(&self) -> Vec<usize> {
+                ArrayD::shape(self).to_vec()
+            }
+
+            fn element_type(&self) -> ElementType {
+                $element_type
+            }
+
+            /// Borrows the memory of standard layout arrays, copies others
+            fn bytes(&self) -> Result<Cow<'_, [u8]>, Error> {
+                Ok(array_bytes(&self.view()))
+            }
+        }
+
+        impl FromSnpeTensor for ArrayD<$element> {
+            fn from_snpe_tensor(tensor: SnpeTensor) -> Result<Self, Error> {
+                ArrayD::try_from(tensor)
+            }
+        }
+    };
+}
+
+impl_array_tensor!(f32, ElementType::Float);
+impl_array_tensor!(u8, ElementType::Uint8);
+
+/// Returns the elements of a view in row major order, without copying if
+/// they're already laid out that way
+fn array_bytes<'a, T: PlainElement>(view: &ArrayViewD<'a, T>) -> Cow<'a, [u8]> {
+    match view.to_slice() {
+        Some(values) => Cow::Borrowed(as_bytes(values)),
+        None => {
+            let values: Vec<T> = view.iter().copied().collect();
+            Cow::Owned(as_bytes(&values).to_vec())
+        }
+    }
+}
+
+/// Wraps row major values in an array of the given shape
+fn to_array<T>(shape: &[usize], values: Vec<T>) -> Result<ArrayD<T>, Error> {
+    ArrayD::from_shape_vec(IxDyn(shape), values)
+        .map_err(|error| Error::ShapeMismatch(error.to_string()))
+}
+
+impl<'a> UserBufferMap<'a> {
+    /// Registers the memory of an array view as a buffer without copying.
+    /// The view's strides are handed to SNPE, so any layout works as long as
+    /// the elements are contiguous in memory and no stride is negative.
+    pub fn insert_array<T>(
+        &mut self,
+        name: &str,
+        view: ArrayViewMutD<'a, T>,
+        encoding: UserBufferEncoding,
+    ) -> Result<(), Error>
+    where
+        T: UserBufferElement,
+    {
+        let strides = view
+            .strides()
+            .iter()
+            .map(|&stride| usize::try_from(stride).map(|s| s * size_of::<T>()))
+            .collect::<Result<Vec<usize>, _>>()
+            .map_err(|_| {
+                Error::InvalidArgument(format!(
+                    "Array {} has negative strides {:?}",
+                    name,
+                    view.strides()
+                ))
+            })?;
+
+        // With non negative strides the first element is also the lowest address
+        let Some(buffer) = view.into_slice_memory_order() else {
+            return Err(Error::InvalidArgument(format!(
+                "Array {} isn't contiguous in memory, copy it into a standard layout array first",
+                name
+            )));
+        };
+
+        self.insert(name, buffer, &strides, encoding)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use ndarray::{ArrayD, IxDyn};
+
+    use crate::snpe::{AsSnpeTensor, SnpeTensor, TensorData};
+
+    #[test]
+    fn views_copy_in_logical_order() {
+        let array =
+            ArrayD::from_shape_vec(IxDyn(&[2, 3]), (0..6).map(|v| v as f32).collect()).unwrap();
+
+        // The transposed view is column major in memory
+        let tensor = SnpeTensor::from(array.t());
+        assert_eq!(tensor.shape(), &[3, 2]);
+        assert_eq!(
+            tensor.data(),
+            &TensorData::Float(vec![0., 3., 1., 4., 2., 5.])
+        );
+    }
+
+    #[test]
+    fn outputs_become_arrays() {
+        let tensor = SnpeTensor::new(&[2, 2], TensorData::Uint8(vec![1, 2, 3, 4])).unwrap();
+        let array = ArrayD::<u8>::try_from(tensor.clone()).unwrap();
+        assert_eq!(array.shape(), &[2, 2]);
+        assert_eq!(array[[1, 0]], 3);
+
+        assert!(ArrayD::<f32>::try_from(tensor).is_err());
+    }
+
+    #[test]
+    fn standard_layout_bytes_are_borrowed() {
+        let array = ArrayD::from_shape_vec(IxDyn(&[2, 2]), vec![1u8, 2, 3, 4]).unwrap();
+        assert!(matches!(array.bytes(), Ok(Cow::Borrowed(&[1, 2, 3, 4]))));
+
+        let transposed = array.t();
+        assert_eq!(
+            AsSnpeTensor::bytes(&transposed).unwrap().as_ref(),
+            &[1, 3, 2, 4]
+        );
+    }
+}
@@ -0,0 +1,166 @@
+use std::borrow::Cow;
+
+use tch::kind::Element;
+use tch::{Device, Kind};
+
+use super::{AsSnpeTensor, FromSnpeTensor, SnpeTensor, TensorData};
+use crate::errors::Error;
+use crate::snpe::ElementType;
+
+impl TryFrom<&tch::Tensor> for SnpeTensor {
+    type Error = Error;
+
+    /// Copies the tensor to the cpu, keeping its layout. Other float types are
+    /// converted to f32 and i16 to i32. Use SnpeTensor::from_tch_nchw for
+    /// PyTorch image batches.
+    fn try_from(tensor: &tch::Tensor) -> Result<Self, Self::Error> {
+        let shape: Vec<usize> = tensor.size().iter().map(|&d| d as usize).collect();
+
+        // copy_data reads the raw storage, so it has to be contiguous on the cpu
+        let tensor = tensor.f_to_device(Device::Cpu)?.f_contiguous()?;
+
+        let data = match tensor.kind() {
+            Kind::Float => TensorData::Float(copy_data(&tensor)?),
+            Kind::Double | Kind::Half | Kind::BFloat16 => {
+                TensorData::Float(copy_data(&tensor.f_to_kind(Kind::Float)?)?)
+            }
+            Kind::Uint8 => TensorData::Uint8(copy_data(&tensor)?),
+            Kind::Int => TensorData::Int32(copy_data(&tensor)?),
+            Kind::Int16 => TensorData::Int32(copy_data(&tensor.f_to_kind(Kind::Int)?)?),
+            kind => {
+                return Err(Error::UnsupportedElementType(format!(
+                    "tch tensors of kind {:?}",
+                    kind
+                )))
+            }
+        };
+
+        SnpeTensor::new(&shape, data)
+    }
+}
+
+impl TryFrom<SnpeTensor> for tch::Tensor {
+    type Error = Error;
+
+    /// Copies the tensor into torch, keeping its layout. Torch has no u16
+    /// type, so u16 tensors become i32.
+    fn try_from(tensor: SnpeTensor) -> Result<Self, Self::Error> {
+        let size: Vec<i64> = tensor.shape().iter().map(|&d| d as i64).collect();
+
+        let flat = match tensor.into_data() {
+            TensorData::Float(values) => tch::Tensor::f_from_slice(&values)?,
+            TensorData::Uint8(values) => tch::Tensor::f_from_slice(&values)?,
+            TensorData::Uint16(values) => {
+                let values: Vec<i32> = values.into_iter().map(i32::from).collect();
+                tch::Tensor::f_from_slice(&values)?
+            }
+            TensorData::Int32(values) => tch::Tensor::f_from_slice(&values)?,
+        };
+
+        Ok(flat.f_reshape(size)?)
+    }
+}
+
+impl AsSnpeTensor for tch::Tensor {
+    fn shape(&self) -> Vec<usize> {
+        self.size().iter().map(|&d| d as usize).collect()
+    }
+
+    /// Returns the type the elements are converted to, or Unknown for kinds
+    /// SNPE can't take
+    fn element_type(&self) -> ElementType {
+        match self.kind() {
+            Kind::Float | Kind::Double | Kind::Half | Kind::BFloat16 => ElementType::Float,
+            Kind::Uint8 => ElementType::Uint8,
+            Kind::Int | Kind::Int16 => ElementType::Int32,
+            _ => ElementType::Unknown,
+        }
+    }
+
+    /// Copies the converted elements, failing for kinds SNPE can't take
+    fn bytes(&self) -> Result<Cow<'_, [u8]>, Error> {
+        let tensor = SnpeTensor::try_from(self)?;
+        Ok(Cow::Owned(tensor.into_data().as_bytes().to_vec()))
+    }
+
+    fn to_snpe_tensor(&self) -> Result<SnpeTensor, Error> {
+        SnpeTensor::try_from(self)
+    }
+}
+
+impl FromSnpeTensor for tch::Tensor {
+    fn from_snpe_tensor(tensor: SnpeTensor) -> Result<Self, Error> {
+        tch::Tensor::try_from(tensor)
+    }
+}
+
+impl SnpeTensor {
+    /// Converts a PyTorch image batch in NCHW layout to an NHWC tensor
+    pub fn from_tch_nchw(tensor: &tch::Tensor) -> Result<Self, Error> {
+        if tensor.dim() != 4 {
+            return Err(Error::ShapeMismatch(format!(
+                "Expected an NCHW tensor but the shape is {:?}",
+                tensor.size()
+            )));
+        }
+
+        // Let torch do the permute, the copy to contiguous memory is needed anyway
+        SnpeTensor::try_from(&tensor.f_permute([0, 2, 3, 1])?)
+    }
+
+    /// Converts an NHWC image batch to a PyTorch tensor in NCHW layout
+    pub fn into_tch_nchw(self) -> Result<tch::Tensor, Error> {
+        self.check_rank(4)?;
+
+        let tensor = tch::Tensor::try_from(self)?;
+        Ok(tensor.f_permute([0, 3, 1, 2])?.f_contiguous()?)
+    }
+}
+
+/// Copies the elements of a contiguous cpu tensor
+fn copy_data<T: Element + Default>(tensor: &tch::Tensor) -> Result<Vec<T>, Error> {
+    let numel = tensor.numel();
+    let mut values = vec![T::default(); numel];
+    tensor.f_copy_data(&mut values, numel)?;
+
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use tch::Kind;
+
+    use crate::errors::Error;
+    use crate::snpe::{AsSnpeTensor, SnpeTensor, TensorData};
+
+    #[test]
+    fn nchw_round_trip() {
+        let nchw = tch::Tensor::arange(12, (Kind::Float, tch::Device::Cpu)).reshape([1, 2, 2, 3]);
+
+        let nhwc = SnpeTensor::from_tch_nchw(&nchw).unwrap();
+        assert_eq!(nhwc.shape(), &[1, 2, 3, 2]);
+        assert_eq!(
+            nhwc.data(),
+            &TensorData::Float(vec![0., 6., 1., 7., 2., 8., 3., 9., 4., 10., 5., 11.])
+        );
+
+        let back = nhwc.into_tch_nchw().unwrap();
+        assert!(back.equal(&nchw));
+    }
+
+    #[test]
+    fn u16_becomes_i32() {
+        let tensor = SnpeTensor::new(&[3], TensorData::Uint16(vec![1, 2, 65535])).unwrap();
+        let converted = tch::Tensor::try_from(tensor).unwrap();
+        assert_eq!(converted.kind(), Kind::Int);
+    }
+
+    #[test]
+    fn unsupported_kinds_fail() {
+        let tensor = tch::Tensor::ones([2], (Kind::Bool, tch::Device::Cpu));
+        assert!(matches!(
+            tensor.bytes(),
+            Err(Error::UnsupportedElementType(_))
+        ));
+    }
+}
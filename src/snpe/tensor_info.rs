@@ -0,0 +1,213 @@
+use std::ffi::CStr;
+
+use super::snpe_bindings;
+use super::user_buffer::{packed_strides, UserBufferEncoding};
+use crate::errors::Error;
+
+/// Data type of the elements in a tensor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ElementType {
+    Unknown,
+    Float,
+    Float16,
+    Unsigned8Bit,
+    Tf8,
+    Tf16,
+    Int8,
+    Int16,
+    Int32,
+    Int64,
+    Uint8,
+    Uint16,
+    Uint32,
+    Uint64,
+    Bool8,
+}
+
+impl ElementType {
+    /// Converts the SNPE element type id
+    fn from_id(id: snpe_bindings::Snpe_UserBufferEncoding_ElementType_t) -> Self {
+        use snpe_bindings::*;
+
+        match id {
+            Snpe_UserBufferEncoding_ElementType_t_SNPE_USERBUFFERENCODING_ELEMENTTYPE_FLOAT => {
+                ElementType::Float
+            }
+            Snpe_UserBufferEncoding_ElementType_t_SNPE_USERBUFFERENCODING_ELEMENTTYPE_FLOAT16 => {
+                ElementType::Float16
+            }
+            Snpe_UserBufferEncoding_ElementType_t_SNPE_USERBUFFERENCODING_ELEMENTTYPE_UNSIGNED8BIT => {
+                ElementType::Unsigned8Bit
+            }
+            Snpe_UserBufferEncoding_ElementType_t_SNPE_USERBUFFERENCODING_ELEMENTTYPE_TF8 => {
+                ElementType::Tf8
+            }
+            Snpe_UserBufferEncoding_ElementType_t_SNPE_USERBUFFERENCODING_ELEMENTTYPE_TF16 => {
+                ElementType::Tf16
+            }
+            Snpe_UserBufferEncoding_ElementType_t_SNPE_USERBUFFERENCODING_ELEMENTTYPE_INT8 => {
+                ElementType::Int8
+            }
+            Snpe_UserBufferEncoding_ElementType_t_SNPE_USERBUFFERENCODING_ELEMENTTYPE_INT16 => {
+                ElementType::Int16
+            }
+            Snpe_UserBufferEncoding_ElementType_t_SNPE_USERBUFFERENCODING_ELEMENTTYPE_INT32 => {
+                ElementType::Int32
+            }
+            Snpe_UserBufferEncoding_ElementType_t_SNPE_USERBUFFERENCODING_ELEMENTTYPE_INT64 => {
+                ElementType::Int64
+            }
+            Snpe_UserBufferEncoding_ElementType_t_SNPE_USERBUFFERENCODING_ELEMENTTYPE_UINT8 => {
+                ElementType::Uint8
+            }
+            Snpe_UserBufferEncoding_ElementType_t_SNPE_USERBUFFERENCODING_ELEMENTTYPE_UINT16 => {
+                ElementType::Uint16
+            }
+            Snpe_UserBufferEncoding_ElementType_t_SNPE_USERBUFFERENCODING_ELEMENTTYPE_UINT32 => {
+                ElementType::Uint32
+            }
+            Snpe_UserBufferEncoding_ElementType_t_SNPE_USERBUFFERENCODING_ELEMENTTYPE_UINT64 => {
+                ElementType::Uint64
+            }
+            Snpe_UserBufferEncoding_ElementType_t_SNPE_USERBUFFERENCODING_ELEMENTTYPE_BOOL8 => {
+                ElementType::Bool8
+            }
+            _ => ElementType::Unknown,
+        }
+    }
+}
+
+/// Fixed point quantization parameters of a tf8/tf16 tensor
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quantization {
+    /// Quantized value that represents 0.0
+    pub step_exactly_0: u64,
+    /// Size of a single quantization step
+    pub step_size: f32,
+    /// Scale to convert quantized values back to floats
+    pub delta: f32,
+    /// Offset to convert quantized values back to floats
+    pub offset: f32,
+}
+
+impl Quantization {
+    /// Converts a quantized value to the float it represents
+    pub fn dequantize(&self, value: f32) -> f32 {
+        (value - self.step_exactly_0 as f32) * self.step_size
+    }
+}
+
+/// Description of an input or output tensor of a network
+#[derive(Debug, Clone, PartialEq)]
+pub struct TensorInfo {
+    pub name: String,
+    /// Dimensions of the tensor
+    pub shape: Vec<usize>,
+    /// Type of the elements in the native buffer
+    pub element_type: ElementType,
+    /// Size in bytes of a single element in the native buffer
+    pub element_size: usize,
+    /// Alignment requirements of each dimension
+    pub alignments: Vec<usize>,
+    /// Quantization parameters, only present for fixed point tensors
+    pub quantization: Option<Quantization>,
+}
+
+impl TensorInfo {
+    /// Reads the buffer attributes of the named tensor from the network
+    pub(crate) fn from_network(
+        network: snpe_bindings::Snpe_SNPE_Handle_t,
+        name: &CStr,
+    ) -> Result<TensorInfo, Error> {
+        unsafe {
+            let snpe = snpe_bindings::loaded();
+            let attributes = snpe.Snpe_SNPE_GetInputOutputBufferAttributes(network, name.as_ptr());
+
+            if attributes.is_null() {
+                return Err(Error::last(&snpe));
+            }
+
+            let dims = snpe.Snpe_IBufferAttributes_GetDims(attributes);
+            let shape = read_shape(&snpe, dims);
+            snpe.Snpe_TensorShape_Delete(dims);
+
+            let aligns = snpe.Snpe_IBufferAttributes_GetAlignments(attributes);
+            let alignments = read_shape(&snpe, aligns);
+            snpe.Snpe_TensorShape_Delete(aligns);
+
+            let element_type =
+                ElementType::from_id(snpe.Snpe_IBufferAttributes_GetEncodingType(attributes));
+            let element_size = snpe.Snpe_IBufferAttributes_GetElementSize(attributes);
+
+            let quantization = match element_type {
+                ElementType::Tf8 | ElementType::Tf16 => {
+                    let encoding = snpe.Snpe_IBufferAttributes_GetEncoding_Ref(attributes);
+                    Some(Quantization {
+                        step_exactly_0: snpe.Snpe_UserBufferEncodingTfN_GetStepExactly0(encoding),
+                        step_size: snpe.Snpe_UserBufferEncodingTfN_GetQuantizedStepSize(encoding),
+                        delta: snpe.Snpe_UserBufferEncodingTfN_GetDelta(encoding),
+                        offset: snpe.Snpe_UserBufferEncodingTfN_GetOffset(encoding),
+                    })
+                }
+                _ => None,
+            };
+
+            snpe.Snpe_IBufferAttributes_Delete(attributes);
+
+            Ok(TensorInfo {
+                name: name.to_string_lossy().to_string(),
+                shape,
+                element_type,
+                element_size,
+                alignments,
+                quantization,
+            })
+        }
+    }
+
+    /// Returns the number of elements in the tensor
+    pub fn num_elements(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    /// Returns the size in bytes of a tightly packed native buffer
+    pub fn size_bytes(&self) -> usize {
+        self.num_elements() * self.element_size
+    }
+
+    /// Returns the byte strides of a tightly packed native buffer
+    pub fn packed_strides(&self) -> Vec<usize> {
+        packed_strides(&self.shape, self.element_size)
+    }
+
+    /// Returns the user buffer encoding matching the native buffer, if the
+    /// element type can be used as a user buffer
+    pub fn encoding(&self) -> Option<UserBufferEncoding> {
+        match (self.element_type, self.quantization) {
+            (ElementType::Float, _) => Some(UserBufferEncoding::Float),
+            (ElementType::Unsigned8Bit, _) => Some(UserBufferEncoding::Unsigned8Bit),
+            (ElementType::Tf8, Some(q)) => {
+                Some(UserBufferEncoding::tf8(q.step_exactly_0, q.step_size))
+            }
+            (ElementType::Tf16, Some(q)) => {
+                Some(UserBufferEncoding::tf16(q.step_exactly_0, q.step_size))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Copies the dimensions out of a c tensor shape
+pub(crate) unsafe fn read_shape(
+    snpe: &snpe_bindings::SNPE,
+    shape: snpe_bindings::Snpe_TensorShape_Handle_t,
+) -> Vec<usize> {
+    if shape.is_null() {
+        return vec![];
+    }
+
+    let rank = snpe.Snpe_TensorShape_Rank(shape);
+    (0..rank)
+        .map(|i| snpe.Snpe_TensorShape_At(shape, i))
+        .collect()
+}
@@ -0,0 +1,283 @@
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::marker::PhantomData;
+use std::mem::size_of;
+
+use super::snpe_bindings;
+use crate::errors::Error;
+
+mod sealed {
+    /// Keeps UserBufferElement to the types below, since buffers are read
+    /// back as them from whatever bytes SNPE wrote
+    pub trait Sealed {}
+
+    impl Sealed for f32 {}
+    impl Sealed for u8 {}
+    impl Sealed for u16 {}
+}
+
+/// Element types that can back a user buffer. Only f32, u8 and u16 do.
+pub trait UserBufferElement: sealed::Sealed + Copy + 'static {}
+
+impl UserBufferElement for f32 {}
+impl UserBufferElement for u8 {}
+impl UserBufferElement for u16 {}
+
+/// How the elements of a user buffer are encoded
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UserBufferEncoding {
+    /// 32 bit floating point
+    Float,
+    /// Unsigned 8 bit integers, passed through without quantization
+    Unsigned8Bit,
+    /// Fixed point quantized values, where `step_exactly_0` is the quantized
+    /// value that maps to 0.0 and `bit_width` is 8 or 16
+    TfN {
+        step_exactly_0: u64,
+        step_size: f32,
+        bit_width: u8,
+    },
+}
+
+impl UserBufferEncoding {
+    /// 8 bit fixed point encoding
+    pub fn tf8(step_exactly_0: u64, step_size: f32) -> Self {
+        UserBufferEncoding::TfN {
+            step_exactly_0,
+            step_size,
+            bit_width: 8,
+        }
+    }
+
+    /// 16 bit fixed point encoding
+    pub fn tf16(step_exactly_0: u64, step_size: f32) -> Self {
+        UserBufferEncoding::TfN {
+            step_exactly_0,
+            step_size,
+            bit_width: 16,
+        }
+    }
+
+    /// Returns the size in bytes of a single element
+    pub fn element_size(&self) -> usize {
+        match self {
+            UserBufferEncoding::Float => 4,
+            UserBufferEncoding::Unsigned8Bit => 1,
+            UserBufferEncoding::TfN { bit_width, .. } => (*bit_width as usize + 7) / 8,
+        }
+    }
+
+    /// Creates the c encoding object
+    unsafe fn create(
+        &self,
+        snpe: &snpe_bindings::SNPE,
+    ) -> snpe_bindings::Snpe_UserBufferEncoding_Handle_t {
+        match self {
+            UserBufferEncoding::Float => snpe.Snpe_UserBufferEncodingFloat_Create(),
+            UserBufferEncoding::Unsigned8Bit => snpe.Snpe_UserBufferEncodingUnsigned8Bit_Create(),
+            UserBufferEncoding::TfN {
+                step_exactly_0,
+                step_size,
+                bit_width,
+            } => snpe.Snpe_UserBufferEncodingTfN_Create(*step_exactly_0, *step_size, *bit_width),
+        }
+    }
+
+    /// Frees a c encoding object previously created by this encoding
+    unsafe fn delete(
+        &self,
+        snpe: &snpe_bindings::SNPE,
+        handle: snpe_bindings::Snpe_UserBufferEncoding_Handle_t,
+    ) {
+        match self {
+            UserBufferEncoding::Float => snpe.Snpe_UserBufferEncodingFloat_Delete(handle),
+            UserBufferEncoding::Unsigned8Bit => {
+                snpe.Snpe_UserBufferEncodingUnsigned8Bit_Delete(handle)
+            }
+            UserBufferEncoding::TfN { .. } => snpe.Snpe_UserBufferEncodingTfN_Delete(handle),
+        };
+    }
+}
+
+/// Returns the byte strides of a tightly packed buffer with the given dimensions
+pub fn packed_strides(dims: &[usize], element_size: usize) -> Vec<usize> {
+    let mut strides = vec![element_size; dims.len()];
+    for i in (0..dims.len().saturating_sub(1)).rev() {
+        strides[i] = strides[i + 1] * dims[i + 1];
+    }
+
+    strides
+}
+
+/// A caller owned slice wrapped as an IUserBuffer
+struct UserBuffer {
+    handle: snpe_bindings::Snpe_IUserBuffer_Handle_t,
+    encoding: UserBufferEncoding,
+    encoding_handle: snpe_bindings::Snpe_UserBufferEncoding_Handle_t,
+    data: *mut u8,
+    len: usize,
+    element: TypeId,
+}
+
+impl Drop for UserBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            let snpe = snpe_bindings::loaded();
+            snpe.Snpe_IUserBuffer_Delete(self.handle);
+            self.encoding.delete(&snpe, self.encoding_handle);
+        }
+    }
+}
+
+/// Map of tensor names to caller owned buffers, used to run the network
+/// without copying. The buffers are borrowed for the lifetime of the map, so
+/// they can't be dropped or moved while SNPE may still read or write them.
+pub struct UserBufferMap<'a> {
+    handle: snpe_bindings::Snpe_UserBufferMap_Handle_t,
+    buffers: HashMap<String, UserBuffer>,
+    _marker: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> UserBufferMap<'a> {
+    /// Creates a new empty user buffer map
+    pub fn new() -> Result<Self, Error> {
+        let snpe = unsafe { snpe_bindings::get()? };
+        let handle = unsafe { snpe.Snpe_UserBufferMap_Create() };
+
+        if handle.is_null() {
+            return Err(unsafe { Error::last(&snpe) });
+        }
+
+        Ok(Self {
+            handle,
+            buffers: HashMap::new(),
+            _marker: PhantomData,
+        })
+    }
+
+    /// Returns the internal handle to the c object
+    pub(crate) fn handle(&self) -> snpe_bindings::Snpe_UserBufferMap_Handle_t {
+        self.handle
+    }
+
+    /// Registers a buffer under the given tensor name, replacing any buffer
+    /// previously registered with that name. `strides` are in bytes, one per
+    /// dimension of the tensor.
+    pub fn insert<T>(
+        &mut self,
+        name: &str,
+        buffer: &'a mut [T],
+        strides: &[usize],
+        encoding: UserBufferEncoding,
+    ) -> Result<(), Error>
+    where
+        T: UserBufferElement,
+    {
+        if encoding.element_size() != size_of::<T>() {
+            return Err(Error::InvalidArgument(format!(
+                "Encoding {:?} doesn't match the {} byte elements of buffer {}",
+                encoding,
+                size_of::<T>(),
+                name
+            )));
+        }
+
+        let cname = CString::new(name)?;
+        let data = buffer.as_mut_ptr() as *mut u8;
+        let size = buffer.len() * size_of::<T>();
+
+        let user_buffer = unsafe {
+            let snpe = snpe_bindings::loaded();
+            let encoding_handle = encoding.create(&snpe);
+            let stride_shape =
+                snpe.Snpe_TensorShape_CreateDimsSize(strides.as_ptr(), strides.len());
+            let handle = snpe.Snpe_Util_CreateUserBuffer(
+                data as *mut std::ffi::c_void,
+                size,
+                stride_shape,
+                encoding_handle,
+            );
+            snpe.Snpe_TensorShape_Delete(stride_shape);
+
+            if handle.is_null() {
+                encoding.delete(&snpe, encoding_handle);
+                return Err(Error::last(&snpe));
+            }
+
+            snpe.Snpe_UserBufferMap_Add(self.handle, cname.as_ptr(), handle);
+
+            UserBuffer {
+                handle,
+                encoding,
+                encoding_handle,
+                data,
+                len: buffer.len(),
+                element: TypeId::of::<T>(),
+            }
+        };
+
+        // Replacing an entry drops the old buffer, which the map no longer references
+        self.buffers.insert(name.to_string(), user_buffer);
+        Ok(())
+    }
+
+    /// Unregisters the buffer with the given name
+    pub fn remove(&mut self, name: &str) {
+        // Names with nul bytes can't have been registered in the first place
+        let Ok(cname) = CString::new(name) else {
+            return;
+        };
+
+        if let Some(buffer) = self.buffers.remove(name) {
+            unsafe {
+                let snpe = snpe_bindings::loaded();
+                snpe.Snpe_UserBufferMap_Remove(self.handle, cname.as_ptr());
+            }
+
+            drop(buffer);
+        }
+    }
+
+    /// Returns the number of registered buffers
+    pub fn len(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// Reads a registered buffer, e.g. to get results after execution
+    pub fn get<T>(&self, name: &str) -> Option<&[T]>
+    where
+        T: UserBufferElement,
+    {
+        let buffer = self.buffers.get(name)?;
+        if buffer.element != TypeId::of::<T>() {
+            return None;
+        }
+
+        Some(unsafe { std::slice::from_raw_parts(buffer.data as *const T, buffer.len) })
+    }
+
+    /// Writes to a registered buffer, e.g. to fill in the next input
+    pub fn get_mut<T>(&mut self, name: &str) -> Option<&mut [T]>
+    where
+        T: UserBufferElement,
+    {
+        let buffer = self.buffers.get(name)?;
+        if buffer.element != TypeId::of::<T>() {
+            return None;
+        }
+
+        Some(unsafe { std::slice::from_raw_parts_mut(buffer.data as *mut T, buffer.len) })
+    }
+}
+
+impl<'a> Drop for UserBufferMap<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            let snpe = snpe_bindings::loaded();
+            snpe.Snpe_UserBufferMap_Delete(self.handle);
+        }
+
+        // The buffers are dropped after the map that references them
+    }
+}
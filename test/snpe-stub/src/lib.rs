@@ -0,0 +1,771 @@
+//! Test stand-in for libSNPE, implementing the part of the SNPE C api the
+//! crate binds. Containers are text files instead of real .dlc files:
+//!
+//! ```text
+//! snpe-stub-dlc
+//! input data 1,4
+//! output prob 1,2 0.25
+//! record meta any text
+//! ```
+//!
+//! A network built from a container takes the listed inputs and fills every
+//! output with its value. Only the cpu runtime is available, and the library
+//! reports the version in SNPE_STUB_VERSION, 2.26.0 by default.
+#![allow(non_snake_case, clippy::missing_safety_doc)]
+
+use std::cell::RefCell;
+use std::ffi::{c_char, c_int, c_void, CStr, CString};
+use std::{fs, ptr, slice};
+
+type Handle = *mut c_void;
+type ErrorCode = u32;
+
+// Values from DlSystem/DlError.h, DlSystem/DlEnums.h and IUserBuffer.h
+const SUCCESS: ErrorCode = 0;
+const BAD_HANDLE: ErrorCode = 13;
+const BAD_ARGUMENT: ErrorCode = 14;
+const MISSING_RECORDS: ErrorCode = 307;
+const WRITE_FAILURE: ErrorCode = 309;
+const READ_FAILURE: ErrorCode = 310;
+/// First code of the runtime block
+const RUNTIME_UNAVAILABLE: ErrorCode = 500;
+const RUNTIME_CPU: c_int = 0;
+const ELEMENT_TYPE_FLOAT: u32 = 1;
+
+const MAGIC: &str = "snpe-stub-dlc";
+
+thread_local! {
+    static LAST_ERROR: RefCell<(ErrorCode, CString)> = RefCell::new((SUCCESS, CString::default()));
+}
+
+/// Records an error and returns its code
+fn fail(code: ErrorCode, message: &str) -> ErrorCode {
+    let message = CString::new(message).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = (code, message));
+    code
+}
+
+/// Records an error for a function returning a handle
+fn fail_null(code: ErrorCode, message: &str) -> Handle {
+    fail(code, message);
+    ptr::null_mut()
+}
+
+fn into_handle<T>(value: T) -> Handle {
+    Box::into_raw(Box::new(value)) as Handle
+}
+
+unsafe fn from_handle<'a, T>(handle: Handle) -> &'a mut T {
+    &mut *(handle as *mut T)
+}
+
+unsafe fn delete<T>(handle: Handle) -> ErrorCode {
+    if handle.is_null() {
+        return fail(BAD_HANDLE, "Null handle");
+    }
+
+    drop(Box::from_raw(handle as *mut T));
+    SUCCESS
+}
+
+unsafe fn str_arg<'a>(ptr: *const c_char) -> Option<&'a str> {
+    match ptr.is_null() {
+        true => None,
+        false => CStr::from_ptr(ptr).to_str().ok(),
+    }
+}
+
+#[derive(Clone)]
+struct TensorSpec {
+    name: String,
+    dims: Vec<usize>,
+    value: f32,
+}
+
+#[derive(Clone, Default)]
+struct Container {
+    inputs: Vec<TensorSpec>,
+    outputs: Vec<TensorSpec>,
+    records: Vec<(String, Vec<u8>)>,
+}
+
+impl Container {
+    fn parse(text: &str) -> Option<Self> {
+        let mut lines = text.lines();
+        if lines.next()?.trim() != MAGIC {
+            return None;
+        }
+
+        let mut container = Container::default();
+        for line in lines.filter(|line| !line.trim().is_empty()) {
+            let mut words = line.splitn(3, ' ');
+            let kind = words.next()?;
+            let name = words.next()?.to_string();
+            let rest = words.next().unwrap_or("");
+
+            match kind {
+                "record" => container.records.push((name, rest.as_bytes().to_vec())),
+                "input" | "output" => {
+                    let mut fields = rest.split_whitespace();
+                    let dims = fields
+                        .next()?
+                        .split(',')
+                        .map(|dim| dim.parse().ok())
+                        .collect::<Option<Vec<usize>>>()?;
+                    let value = fields.next().map_or(Some(0.0), |v| v.parse().ok())?;
+
+                    let spec = TensorSpec { name, dims, value };
+                    match kind {
+                        "input" => container.inputs.push(spec),
+                        _ => container.outputs.push(spec),
+                    }
+                }
+                _ => return None,
+            }
+        }
+
+        Some(container)
+    }
+
+    fn to_text(&self) -> String {
+        let mut text = format!("{}\n", MAGIC);
+        let dims = |dims: &[usize]| {
+            dims.iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+
+        for spec in &self.inputs {
+            text += &format!("input {} {}\n", spec.name, dims(&spec.dims));
+        }
+        for spec in &self.outputs {
+            text += &format!("output {} {} {}\n", spec.name, dims(&spec.dims), spec.value);
+        }
+        for (name, data) in &self.records {
+            text += &format!("record {} {}\n", name, String::from_utf8_lossy(data));
+        }
+
+        text
+    }
+}
+
+struct Record {
+    name: CString,
+    data: Vec<u8>,
+}
+
+struct Tensor {
+    dims: Vec<usize>,
+    data: Vec<f32>,
+}
+
+#[derive(Default)]
+struct TensorMap {
+    /// The map only references tensors added to it, but owns those it creates
+    tensors: Vec<(CString, Handle, bool)>,
+}
+
+impl Drop for TensorMap {
+    fn drop(&mut self) {
+        for &(_, tensor, owned) in &self.tensors {
+            if owned {
+                unsafe { delete::<Tensor>(tensor) };
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Builder {
+    container: Container,
+    runtimes: Option<Vec<c_int>>,
+}
+
+struct Network {
+    container: Container,
+}
+
+struct BufferAttributes {
+    dims: Vec<usize>,
+}
+
+fn library_version() -> (i32, i32, i32) {
+    let version = std::env::var("SNPE_STUB_VERSION").unwrap_or_else(|_| "2.26.0".to_string());
+    let mut parts = version.split('.').map(|part| part.parse().unwrap_or(0));
+
+    (
+        parts.next().unwrap_or(0),
+        parts.next().unwrap_or(0),
+        parts.next().unwrap_or(0),
+    )
+}
+
+// Errors
+
+#[no_mangle]
+pub extern "C" fn Snpe_ErrorCode_getLastErrorCode() -> ErrorCode {
+    LAST_ERROR.with(|last| last.borrow().0)
+}
+
+#[no_mangle]
+pub extern "C" fn Snpe_ErrorCode_GetLastErrorString() -> *const c_char {
+    // The string lives until the next error on this thread
+    LAST_ERROR.with(|last| last.borrow().1.as_ptr())
+}
+
+#[no_mangle]
+pub extern "C" fn Snpe_ErrorCode_getLastInfo() -> *const c_char {
+    c"stub".as_ptr()
+}
+
+// Versions and runtimes
+
+#[no_mangle]
+pub extern "C" fn Snpe_Util_GetLibraryVersion() -> Handle {
+    into_handle(library_version())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_DlVersion_GetMajor(handle: Handle) -> i32 {
+    from_handle::<(i32, i32, i32)>(handle).0
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_DlVersion_GetMinor(handle: Handle) -> i32 {
+    from_handle::<(i32, i32, i32)>(handle).1
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_DlVersion_GetTeeny(handle: Handle) -> i32 {
+    from_handle::<(i32, i32, i32)>(handle).2
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_DlVersion_Delete(handle: Handle) -> ErrorCode {
+    delete::<(i32, i32, i32)>(handle)
+}
+
+#[no_mangle]
+pub extern "C" fn Snpe_Util_IsRuntimeAvailable(runtime: c_int) -> c_int {
+    (runtime == RUNTIME_CPU) as c_int
+}
+
+#[no_mangle]
+pub extern "C" fn Snpe_RuntimeList_Create() -> Handle {
+    into_handle(Vec::<c_int>::new())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_RuntimeList_Add(handle: Handle, runtime: c_int) -> ErrorCode {
+    let list = from_handle::<Vec<c_int>>(handle);
+    if list.contains(&runtime) {
+        return fail(BAD_ARGUMENT, "Runtime is already in the list");
+    }
+
+    list.push(runtime);
+    SUCCESS
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_RuntimeList_Size(handle: Handle) -> usize {
+    from_handle::<Vec<c_int>>(handle).len()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_RuntimeList_GetRuntime(handle: Handle, index: c_int) -> c_int {
+    let list = from_handle::<Vec<c_int>>(handle);
+    list.get(index as usize).copied().unwrap_or(-1)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_RuntimeList_Delete(handle: Handle) -> ErrorCode {
+    delete::<Vec<c_int>>(handle)
+}
+
+// String lists
+
+#[no_mangle]
+pub extern "C" fn Snpe_StringList_Create() -> Handle {
+    into_handle(Vec::<CString>::new())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_StringList_Append(
+    handle: Handle,
+    string: *const c_char,
+) -> ErrorCode {
+    if string.is_null() {
+        return fail(BAD_ARGUMENT, "Null string");
+    }
+
+    from_handle::<Vec<CString>>(handle).push(CStr::from_ptr(string).to_owned());
+    SUCCESS
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_StringList_Size(handle: Handle) -> usize {
+    from_handle::<Vec<CString>>(handle).len()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_StringList_At(handle: Handle, index: usize) -> *const c_char {
+    match from_handle::<Vec<CString>>(handle).get(index) {
+        Some(string) => string.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_StringList_Delete(handle: Handle) -> ErrorCode {
+    delete::<Vec<CString>>(handle)
+}
+
+fn string_list<'a>(strings: impl IntoIterator<Item = &'a str>) -> Handle {
+    let strings: Vec<CString> = strings
+        .into_iter()
+        .map(|s| CString::new(s).unwrap_or_default())
+        .collect();
+    into_handle(strings)
+}
+
+// Containers and records
+
+fn open_container(bytes: &[u8]) -> Handle {
+    match std::str::from_utf8(bytes).ok().and_then(Container::parse) {
+        Some(container) => into_handle(container),
+        None => fail_null(READ_FAILURE, "Not a stub container"),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_DlContainer_Open(filename: *const c_char) -> Handle {
+    let Some(path) = str_arg(filename) else {
+        return fail_null(BAD_ARGUMENT, "Invalid file name");
+    };
+
+    match fs::read(path) {
+        Ok(bytes) => open_container(&bytes),
+        Err(e) => fail_null(READ_FAILURE, &format!("Failed to read {}: {}", path, e)),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_DlContainer_OpenBuffer(buffer: *const u8, size: usize) -> Handle {
+    if buffer.is_null() {
+        return fail_null(BAD_ARGUMENT, "Null buffer");
+    }
+
+    open_container(slice::from_raw_parts(buffer, size))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_DlContainer_Save(
+    handle: Handle,
+    filename: *const c_char,
+) -> ErrorCode {
+    let Some(path) = str_arg(filename) else {
+        return fail(BAD_ARGUMENT, "Invalid file name");
+    };
+
+    match fs::write(path, from_handle::<Container>(handle).to_text()) {
+        Ok(()) => SUCCESS,
+        Err(e) => fail(WRITE_FAILURE, &format!("Failed to write {}: {}", path, e)),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_DlContainer_GetCatalog(handle: Handle) -> Handle {
+    let container = from_handle::<Container>(handle);
+    string_list(container.records.iter().map(|(name, _)| name.as_str()))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_DlContainer_GetRecord(handle: Handle, name: *const c_char) -> Handle {
+    let container = from_handle::<Container>(handle);
+    let name = str_arg(name).unwrap_or("");
+
+    match container.records.iter().find(|(record, _)| record == name) {
+        Some((record, data)) => into_handle(Record {
+            name: CString::new(record.as_str()).unwrap_or_default(),
+            data: data.clone(),
+        }),
+        None => fail_null(MISSING_RECORDS, &format!("No record named {}", name)),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_DlContainer_Delete(handle: Handle) -> ErrorCode {
+    delete::<Container>(handle)
+}
+
+#[no_mangle]
+pub extern "C" fn Snpe_DlcRecord_Create() -> Handle {
+    into_handle(Record {
+        name: CString::default(),
+        data: vec![],
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_DlcRecord_CreateName(name: *const c_char) -> Handle {
+    match str_arg(name) {
+        Some(name) => into_handle(Record {
+            name: CString::new(name).unwrap_or_default(),
+            data: vec![],
+        }),
+        None => fail_null(BAD_ARGUMENT, "Invalid record name"),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_DlcRecord_Name(handle: Handle) -> *const c_char {
+    from_handle::<Record>(handle).name.as_ptr()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_DlcRecord_Size(handle: Handle) -> usize {
+    from_handle::<Record>(handle).data.len()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_DlcRecord_Data(handle: Handle) -> *mut u8 {
+    from_handle::<Record>(handle).data.as_mut_ptr()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_DlcRecord_Delete(handle: Handle) -> ErrorCode {
+    delete::<Record>(handle)
+}
+
+// Shapes, tensors and tensor maps
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_TensorShape_CreateDimsSize(
+    dims: *const usize,
+    size: usize,
+) -> Handle {
+    let dims = match dims.is_null() {
+        true => vec![],
+        false => slice::from_raw_parts(dims, size).to_vec(),
+    };
+
+    into_handle(dims)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_TensorShape_Rank(handle: Handle) -> usize {
+    from_handle::<Vec<usize>>(handle).len()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_TensorShape_At(handle: Handle, index: usize) -> usize {
+    from_handle::<Vec<usize>>(handle)[index]
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_TensorShape_Delete(handle: Handle) -> ErrorCode {
+    delete::<Vec<usize>>(handle)
+}
+
+fn new_tensor(dims: &[usize], value: f32) -> Handle {
+    into_handle(Tensor {
+        dims: dims.to_vec(),
+        data: vec![value; dims.iter().product()],
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_Util_CreateITensor(shape: Handle) -> Handle {
+    if shape.is_null() {
+        return fail_null(BAD_HANDLE, "Null shape");
+    }
+
+    new_tensor(from_handle::<Vec<usize>>(shape), 0.0)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_ITensor_GetSize(handle: Handle) -> usize {
+    from_handle::<Tensor>(handle).data.len()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_ITensor_GetData(handle: Handle) -> *mut c_void {
+    from_handle::<Tensor>(handle).data.as_mut_ptr() as *mut c_void
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_ITensor_GetShape(handle: Handle) -> Handle {
+    into_handle(from_handle::<Tensor>(handle).dims.clone())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_ITensor_Delete(handle: Handle) -> ErrorCode {
+    delete::<Tensor>(handle)
+}
+
+#[no_mangle]
+pub extern "C" fn Snpe_TensorMap_Create() -> Handle {
+    into_handle(TensorMap::default())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_TensorMap_Add(handle: Handle, name: *const c_char, tensor: Handle) {
+    if name.is_null() || tensor.is_null() {
+        fail(BAD_ARGUMENT, "Null tensor name or handle");
+        return;
+    }
+
+    let map = from_handle::<TensorMap>(handle);
+    map.tensors
+        .push((CStr::from_ptr(name).to_owned(), tensor, false));
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_TensorMap_GetTensorNames(handle: Handle) -> Handle {
+    let map = from_handle::<TensorMap>(handle);
+    string_list(
+        map.tensors
+            .iter()
+            .filter_map(|(name, _, _)| name.to_str().ok()),
+    )
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_TensorMap_GetTensor_Ref(
+    handle: Handle,
+    name: *const c_char,
+) -> Handle {
+    let map = from_handle::<TensorMap>(handle);
+    let name = CStr::from_ptr(name);
+
+    match map
+        .tensors
+        .iter()
+        .find(|(tensor, _, _)| tensor.as_c_str() == name)
+    {
+        Some(&(_, tensor, _)) => tensor,
+        None => ptr::null_mut(),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_TensorMap_Delete(handle: Handle) -> ErrorCode {
+    delete::<TensorMap>(handle)
+}
+
+// Building and running networks
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_SNPEBuilder_Create(container: Handle) -> Handle {
+    if container.is_null() {
+        return fail_null(BAD_HANDLE, "Null container");
+    }
+
+    into_handle(Builder {
+        container: from_handle::<Container>(container).clone(),
+        runtimes: None,
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn Snpe_SNPEBuilder_SetPerformanceProfile(
+    _handle: Handle,
+    _profile: c_int,
+) -> ErrorCode {
+    SUCCESS
+}
+
+#[no_mangle]
+pub extern "C" fn Snpe_SNPEBuilder_SetUseUserSuppliedBuffers(
+    _handle: Handle,
+    _mode: c_int,
+) -> ErrorCode {
+    SUCCESS
+}
+
+#[no_mangle]
+pub extern "C" fn Snpe_SNPEBuilder_SetCpuFixedPointMode(_handle: Handle, _mode: bool) -> ErrorCode {
+    SUCCESS
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_SNPEBuilder_SetRuntimeProcessorOrder(
+    handle: Handle,
+    list: Handle,
+) -> ErrorCode {
+    from_handle::<Builder>(handle).runtimes = Some(from_handle::<Vec<c_int>>(list).clone());
+    SUCCESS
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_SNPEBuilder_SetOutputLayers(
+    _handle: Handle,
+    _names: Handle,
+) -> ErrorCode {
+    SUCCESS
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_SNPEBuilder_SetOutputTensors(
+    handle: Handle,
+    names: Handle,
+) -> ErrorCode {
+    let builder = from_handle::<Builder>(handle);
+    let names = from_handle::<Vec<CString>>(names);
+
+    for name in names.iter() {
+        if !builder
+            .container
+            .outputs
+            .iter()
+            .any(|spec| spec.name.as_bytes() == name.as_bytes())
+        {
+            return fail(BAD_ARGUMENT, &format!("Unknown output tensor {:?}", name));
+        }
+    }
+
+    builder.container.outputs.retain(|spec| {
+        names
+            .iter()
+            .any(|name| spec.name.as_bytes() == name.as_bytes())
+    });
+    SUCCESS
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_SNPEBuilder_Build(handle: Handle) -> Handle {
+    let builder = from_handle::<Builder>(handle);
+
+    if let Some(runtimes) = &builder.runtimes {
+        if !runtimes.contains(&RUNTIME_CPU) {
+            return fail_null(RUNTIME_UNAVAILABLE, "Only the cpu runtime is available");
+        }
+    }
+
+    into_handle(Network {
+        container: builder.container.clone(),
+    })
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_SNPEBuilder_Delete(handle: Handle) -> ErrorCode {
+    delete::<Builder>(handle)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_SNPE_GetInputTensorNames(handle: Handle) -> Handle {
+    let network = from_handle::<Network>(handle);
+    string_list(
+        network
+            .container
+            .inputs
+            .iter()
+            .map(|spec| spec.name.as_str()),
+    )
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_SNPE_GetOutputTensorNames(handle: Handle) -> Handle {
+    let network = from_handle::<Network>(handle);
+    string_list(
+        network
+            .container
+            .outputs
+            .iter()
+            .map(|spec| spec.name.as_str()),
+    )
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_SNPE_GetInputOutputBufferAttributes(
+    handle: Handle,
+    name: *const c_char,
+) -> Handle {
+    let network = from_handle::<Network>(handle);
+    let name = str_arg(name).unwrap_or("");
+
+    let container = &network.container;
+    match container
+        .inputs
+        .iter()
+        .chain(&container.outputs)
+        .find(|spec| spec.name == name)
+    {
+        Some(spec) => into_handle(BufferAttributes {
+            dims: spec.dims.clone(),
+        }),
+        None => fail_null(BAD_ARGUMENT, &format!("Unknown tensor {}", name)),
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_SNPE_ExecuteITensors(
+    handle: Handle,
+    inputs: Handle,
+    outputs: Handle,
+) -> ErrorCode {
+    let network = from_handle::<Network>(handle);
+    let inputs = from_handle::<TensorMap>(inputs);
+
+    for spec in &network.container.inputs {
+        let Some(&(_, tensor, _)) = inputs
+            .tensors
+            .iter()
+            .find(|(name, _, _)| name.as_bytes() == spec.name.as_bytes())
+        else {
+            return fail(BAD_ARGUMENT, &format!("Missing input tensor {}", spec.name));
+        };
+
+        let size: usize = spec.dims.iter().product();
+        if from_handle::<Tensor>(tensor).data.len() != size {
+            return fail(
+                BAD_ARGUMENT,
+                &format!("Input {} has the wrong size", spec.name),
+            );
+        }
+    }
+
+    let outputs = from_handle::<TensorMap>(outputs);
+    for spec in &network.container.outputs {
+        let name = CString::new(spec.name.as_str()).unwrap_or_default();
+        outputs
+            .tensors
+            .push((name, new_tensor(&spec.dims, spec.value), true));
+    }
+
+    SUCCESS
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_SNPE_Delete(handle: Handle) -> ErrorCode {
+    delete::<Network>(handle)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_IBufferAttributes_GetDims(handle: Handle) -> Handle {
+    into_handle(from_handle::<BufferAttributes>(handle).dims.clone())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_IBufferAttributes_GetAlignments(handle: Handle) -> Handle {
+    into_handle(vec![
+        1usize;
+        from_handle::<BufferAttributes>(handle).dims.len()
+    ])
+}
+
+#[no_mangle]
+pub extern "C" fn Snpe_IBufferAttributes_GetEncodingType(_handle: Handle) -> u32 {
+    ELEMENT_TYPE_FLOAT
+}
+
+#[no_mangle]
+pub extern "C" fn Snpe_IBufferAttributes_GetElementSize(_handle: Handle) -> usize {
+    std::mem::size_of::<f32>()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn Snpe_IBufferAttributes_Delete(handle: Handle) -> ErrorCode {
+    delete::<BufferAttributes>(handle)
+}
@@ -0,0 +1,173 @@
+//! Runs the crate against the stand-in libSNPE in test/snpe-stub, so the ffi
+//! layer can be tested without the SDK
+
+use std::collections::HashMap;
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+use snpe::{
+    get_available_runtimes, get_version, DlContainer, DlContainerError, DlcRecord, Error, Runtime,
+    Snpe, SnpeLibrary, SnpeTensor, TensorData,
+};
+
+const CONTAINER: &str = "snpe-stub-dlc
+input data 1,4
+output prob 1,2 0.25
+output logits 1,3 -1
+record meta hello
+";
+
+/// Builds the stub and loads it, once per test binary
+fn load_stub() {
+    static LIBRARY: OnceLock<SnpeLibrary> = OnceLock::new();
+
+    LIBRARY.get_or_init(|| {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let target_dir = root.join("target").join("snpe-stub");
+
+        let status = Command::new(env!("CARGO"))
+            .arg("build")
+            .arg("--quiet")
+            .arg("--manifest-path")
+            .arg(root.join("test/snpe-stub/Cargo.toml"))
+            .arg("--target-dir")
+            .arg(&target_dir)
+            .status()
+            .expect("Failed to run cargo");
+        assert!(status.success(), "Failed to build the SNPE stub");
+
+        // The stub reports whatever version the bindings expect
+        std::env::set_var("SNPE_STUB_VERSION", env!("SNPE_HEADER_VERSION"));
+
+        let path = target_dir
+            .join("debug")
+            .join(format!("{}SNPE{}", DLL_PREFIX, DLL_SUFFIX));
+        SnpeLibrary::open(path).unwrap()
+    });
+}
+
+/// Writes a container into a directory of its own
+fn write_container(name: &str, text: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("snpe-stub-{}-{}", std::process::id(), name));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join("model.dlc");
+    std::fs::write(&path, text).unwrap();
+    path
+}
+
+#[test]
+fn version_matches_headers() {
+    load_stub();
+    assert_eq!(get_version().unwrap(), SnpeLibrary::header_version());
+}
+
+#[test]
+fn only_cpu_is_available() {
+    load_stub();
+    assert_eq!(get_available_runtimes(), vec![Runtime::CPU]);
+}
+
+#[test]
+fn read_failures_map_to_container_errors() {
+    load_stub();
+
+    let missing = DlContainer::from_path("does_not_exist.dlc");
+    assert!(matches!(
+        missing,
+        Err(Error::DlContainer(DlContainerError::ReadFailure(_)))
+    ));
+
+    let garbage = DlContainer::from_buffer(b"not a container");
+    assert!(matches!(
+        garbage,
+        Err(Error::DlContainer(DlContainerError::ReadFailure(_)))
+    ));
+}
+
+#[test]
+fn catalog_and_records() {
+    load_stub();
+
+    let path = write_container("catalog", CONTAINER);
+    let container = DlContainer::from_path(path.to_str().unwrap()).unwrap();
+
+    let records = container.get_catalog().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].name(), "meta");
+    assert_eq!(records[0].get_data().unwrap(), b"hello");
+
+    assert!(matches!(
+        container.get_record("missing"),
+        Err(Error::DlContainer(DlContainerError::MissingRecords(_)))
+    ));
+
+    let record = DlcRecord::create(Some("new")).unwrap();
+    assert_eq!(record.name(), "new");
+    assert_eq!(record.size(), 0);
+}
+
+#[test]
+fn save_round_trip() {
+    load_stub();
+
+    let path = write_container("save", CONTAINER);
+    let container = DlContainer::from_path(path.to_str().unwrap()).unwrap();
+
+    let copy = path.with_file_name("copy.dlc");
+    container.save(copy.to_str().unwrap()).unwrap();
+
+    let buffer = std::fs::read(&copy).unwrap();
+    let reopened = DlContainer::from_buffer(&buffer).unwrap();
+    assert_eq!(reopened.get_catalog().unwrap()[0].name(), "meta");
+}
+
+#[test]
+fn execute_returns_canned_outputs() {
+    load_stub();
+
+    let path = write_container("execute", CONTAINER);
+    let container = DlContainer::from_path(path.to_str().unwrap()).unwrap();
+    let network = Snpe::builder(&container)
+        .runtime_order(&[Runtime::CPU])
+        .build()
+        .unwrap();
+
+    let inputs = network.input_tensors().unwrap();
+    assert_eq!(inputs.len(), 1);
+    assert_eq!(inputs[0].name, "data");
+    assert_eq!(inputs[0].shape, vec![1, 4]);
+
+    let data = SnpeTensor::new(&[1, 4], TensorData::Float(vec![1.0; 4])).unwrap();
+    let outputs = network
+        .execute_tensors(&HashMap::from([("data".to_string(), data)]))
+        .unwrap();
+
+    assert_eq!(outputs.len(), 2);
+    assert_eq!(outputs["prob"].shape(), &[1, 2]);
+    assert_eq!(outputs["prob"].data(), &TensorData::Float(vec![0.25; 2]));
+    assert_eq!(outputs["logits"].data(), &TensorData::Float(vec![-1.0; 3]));
+}
+
+#[test]
+fn execute_errors_come_from_the_library() {
+    load_stub();
+
+    let path = write_container("errors", CONTAINER);
+    let container = DlContainer::from_path(path.to_str().unwrap()).unwrap();
+    let network = Snpe::builder(&container).build().unwrap();
+
+    let wrong = SnpeTensor::new(&[2], TensorData::Float(vec![0.0; 2])).unwrap();
+    let result = network.execute_tensors(&HashMap::from([("other".to_string(), wrong)]));
+    match result {
+        Err(Error::Capi(details)) => assert!(details.message.contains("data")),
+        other => panic!("Expected a C api error, got {:?}", other.err()),
+    }
+
+    let gpu_only = Snpe::builder(&container)
+        .runtime_order(&[Runtime::GPU])
+        .build();
+    assert!(matches!(gpu_only, Err(Error::Runtime(_))));
+}